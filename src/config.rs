@@ -3,7 +3,8 @@ use figment::{
     providers::{Env, Format, Serialized, Toml, Yaml},
     Figment,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 use serde_json::Value;
 use std::collections::BTreeMap;
 
@@ -16,6 +17,27 @@ pub struct AppConfig {
     pub sync: SyncConfig,
     #[serde(default)]
     pub database: Vec<DatabaseConfig>,
+    #[serde(default)]
+    pub page: Vec<PageTreeConfig>,
+    #[serde(default)]
+    pub discovery: Option<DiscoveryConfig>,
+    #[serde(default)]
+    pub sqlite: Option<SqliteConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SqliteConfig {
+    pub path: String,
+}
+
+/// Syncs every database and top-level page shared with the integration.
+/// Storage settings must use `{{id}}` and may add `{{title}}` or
+/// `{{slug:title}}`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DiscoveryConfig {
+    #[serde(default)]
+    pub title_pattern: Option<String>,
+    pub storage: Vec<BackendConfig>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -50,17 +72,101 @@ impl Default for WebhookConfig {
 pub struct SyncConfig {
     #[serde(default = "default_sync_interval_seconds")]
     pub interval_seconds: u64,
+    #[serde(default)]
+    pub deleted_pages: DeletedPagesMode,
+    #[serde(default = "default_sync_concurrency")]
+    pub concurrency: usize,
+    #[serde(default)]
+    pub max_depth: MaxDepth,
 }
 
 impl Default for SyncConfig {
     fn default() -> Self {
         Self {
             interval_seconds: default_sync_interval_seconds(),
+            deleted_pages: DeletedPagesMode::default(),
+            concurrency: default_sync_concurrency(),
+            max_depth: MaxDepth::default(),
         }
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaxDepth {
+    Limited(usize),
+    Unlimited,
+}
+
+impl MaxDepth {
+    pub fn levels(self) -> Option<usize> {
+        match self {
+            MaxDepth::Limited(levels) => Some(levels),
+            MaxDepth::Unlimited => None,
+        }
+    }
+}
+
+impl Default for MaxDepth {
+    fn default() -> Self {
+        MaxDepth::Limited(3)
+    }
+}
+
+impl Serialize for MaxDepth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            MaxDepth::Limited(levels) => serializer.serialize_u64(*levels as u64),
+            MaxDepth::Unlimited => serializer.serialize_str("unlimited"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MaxDepth {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct MaxDepthVisitor;
+
+        impl de::Visitor<'_> for MaxDepthVisitor {
+            type Value = MaxDepth;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a non-negative integer or \"unlimited\"")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<MaxDepth, E> {
+                Ok(MaxDepth::Limited(value as usize))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<MaxDepth, E> {
+                usize::try_from(value)
+                    .map(MaxDepth::Limited)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<MaxDepth, E> {
+                match value.trim() {
+                    "unlimited" => Ok(MaxDepth::Unlimited),
+                    other => other
+                        .parse()
+                        .map(MaxDepth::Limited)
+                        .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(MaxDepthVisitor)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletedPagesMode {
+    Delete,
+    #[default]
+    Archive,
+    Keep,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct DatabaseConfig {
     pub id: String,
     #[serde(alias = "storage")]
@@ -69,6 +175,87 @@ pub struct DatabaseConfig {
     pub key_map: BTreeMap<String, String>,
     #[serde(default)]
     pub properties: DatabasePropertiesConfig,
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    #[serde(default)]
+    pub max_depth: Option<MaxDepth>,
+    #[serde(default)]
+    pub render: RenderConfig,
+    #[serde(default)]
+    pub path_template: Option<String>,
+    #[serde(default)]
+    pub format: Vec<FormatConfig>,
+    #[serde(default)]
+    pub tables: Vec<TableFormat>,
+    #[serde(default)]
+    pub child_databases: bool,
+}
+
+/// A standalone page synced together with its sub-pages and inline
+/// databases, mirroring the Notion hierarchy as directories.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PageTreeConfig {
+    pub id: String,
+    pub storage: Vec<BackendConfig>,
+    #[serde(default)]
+    pub directory: Option<String>,
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    #[serde(default)]
+    pub max_depth: Option<MaxDepth>,
+    #[serde(default)]
+    pub render: RenderConfig,
+    #[serde(default)]
+    pub format: Vec<FormatConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FormatConfig {
+    #[serde(rename = "type")]
+    pub r#type: OutputFormat,
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TableFormat {
+    Csv,
+    Jsonl,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RenderConfig {
+    #[serde(default)]
+    pub toggle_style: ToggleStyle,
+    #[serde(default)]
+    pub column_style: ColumnStyle,
+    #[serde(default)]
+    pub colors: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToggleStyle {
+    #[default]
+    Details,
+    Flatten,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnStyle {
+    #[default]
+    Sequential,
+    Grid,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -77,6 +264,35 @@ pub struct DatabasePropertiesConfig {
     pub map: BTreeMap<String, String>,
     #[serde(default)]
     pub filter: PropertyFilterConfig,
+    #[serde(default)]
+    pub transform: BTreeMap<String, PropertyTransform>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PropertyTransform {
+    #[serde(default)]
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub split: Option<String>,
+    #[serde(default)]
+    pub join: Option<String>,
+    #[serde(default)]
+    pub case: Option<CaseTransform>,
+    #[serde(default)]
+    pub slugify: bool,
+    #[serde(default)]
+    pub date_format: Option<String>,
+    #[serde(default)]
+    pub map: BTreeMap<String, String>,
+    #[serde(default)]
+    pub drop_empty: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseTransform {
+    Lower,
+    Upper,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -94,6 +310,33 @@ pub struct BackendConfig {
 }
 
 impl BackendConfig {
+    /// Identifies the backend's location: type, then endpoint, bucket and
+    /// root, so backends sharing a bucket under different roots stay apart.
+    pub fn label(&self) -> String {
+        let mut location = String::new();
+        for key in ["endpoint", "bucket", "root"] {
+            let Some(value) = self.settings.get(key).and_then(value_to_string) else {
+                continue;
+            };
+            let value = if location.is_empty() {
+                value.trim_end_matches('/')
+            } else {
+                value.trim_matches('/')
+            };
+            if !value.is_empty() {
+                if !location.is_empty() {
+                    location.push('/');
+                }
+                location.push_str(value);
+            }
+        }
+        if location.is_empty() {
+            self.r#type.clone()
+        } else {
+            format!("{}:{}", self.r#type, location)
+        }
+    }
+
     pub fn settings_as_strings(&self) -> BTreeMap<String, String> {
         self.settings
             .iter()
@@ -125,14 +368,35 @@ impl AppConfig {
         if config.notion.api_key.trim().is_empty() {
             return Err(anyhow!("notion.api_key is required"));
         }
-        if config.database.is_empty() {
-            return Err(anyhow!("at least one database entry is required"));
+        if config.database.is_empty() && config.page.is_empty() && config.discovery.is_none() {
+            return Err(anyhow!(
+                "at least one database or page entry, or discovery, is required"
+            ));
         }
         for db in &config.database {
             if db.storage.is_empty() {
                 return Err(anyhow!("database {} is missing storage config", db.id));
             }
         }
+        for page in &config.page {
+            if page.storage.is_empty() {
+                return Err(anyhow!("page {} is missing storage config", page.id));
+            }
+        }
+        if let Some(discovery) = config.discovery.as_ref() {
+            // Titles aren't unique, so only the id keeps two discoveries from
+            // sharing a location.
+            let templated = discovery.storage.iter().all(|storage| {
+                storage.settings.values().any(|value| {
+                    value.as_str().is_some_and(|value| value.contains("{{id}}"))
+                })
+            });
+            if discovery.storage.is_empty() || !templated {
+                return Err(anyhow!(
+                    "discovery storage must use {{{{id}}}} so each database gets its own location"
+                ));
+            }
+        }
         Ok(config)
     }
 }
@@ -152,3 +416,39 @@ fn default_webhook_max_age_seconds() -> u64 {
 fn default_sync_interval_seconds() -> u64 {
     86400
 }
+
+fn default_sync_concurrency() -> usize {
+    4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(settings: serde_json::Value) -> BackendConfig {
+        serde_json::from_value(settings).unwrap()
+    }
+
+    #[test]
+    fn label_includes_endpoint_bucket_and_root() {
+        let blog = backend(serde_json::json!({
+            "type": "s3",
+            "endpoint": "https://s3.example.com",
+            "bucket": "notes",
+            "root": "/blog/",
+        }));
+        let wiki = backend(serde_json::json!({
+            "type": "s3",
+            "endpoint": "https://s3.example.com",
+            "bucket": "notes",
+            "root": "/wiki",
+        }));
+        assert_eq!(blog.label(), "s3:https://s3.example.com/notes/blog");
+        assert_ne!(blog.label(), wiki.label());
+        assert_eq!(
+            backend(serde_json::json!({ "type": "fs", "root": "/var/lib/notes" })).label(),
+            "fs:/var/lib/notes"
+        );
+        assert_eq!(backend(serde_json::json!({ "type": "memory" })).label(), "memory");
+    }
+}
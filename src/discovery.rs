@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use log::{info, warn};
+use serde_json::Value;
+
+use crate::config::{BackendConfig, DatabaseConfig, DiscoveryConfig, PageTreeConfig, SyncConfig};
+use crate::notion::{normalize_id, SearchResult};
+use crate::template::slugify;
+use crate::{init_database, init_page_tree, AppState, DatabaseState};
+
+#[derive(Clone)]
+pub struct Discovery {
+    pub config: DiscoveryConfig,
+    pub sync: SyncConfig,
+}
+
+/// Searches the workspace for databases and top-level pages that aren't
+/// synced yet and adds them with storage rendered from the template.
+pub async fn discover(state: &AppState, discovery: &Discovery) {
+    let results = match state.notion.search().await {
+        Ok(results) => results,
+        Err(err) => {
+            warn!("workspace search failed: {err}");
+            return;
+        }
+    };
+    let pattern = discovery.config.title_pattern.as_deref();
+    let matched = results
+        .iter()
+        .filter(|result| {
+            pattern.is_none_or(|pattern| {
+                glob_match(pattern, result.title.as_deref().unwrap_or_default())
+            })
+        })
+        .collect::<Vec<_>>();
+    // Anything below a matched page is synced as part of that page's tree.
+    let pages = matched
+        .iter()
+        .filter(|result| result.object == "page")
+        .map(|result| result.id.as_str())
+        .collect::<HashSet<_>>();
+    let covered = |result: &SearchResult| {
+        result
+            .parent
+            .page_id
+            .as_deref()
+            .is_some_and(|parent| pages.contains(parent))
+    };
+
+    let mut known = state
+        .databases()
+        .iter()
+        .filter_map(|database| normalize_id(&database.id))
+        .collect::<HashSet<_>>();
+    let mut discovered = Vec::new();
+    for result in matched {
+        if covered(result) {
+            continue;
+        }
+        let (kind, id) = match result.object.as_str() {
+            // Pages nested in another page's blocks belong to that page, so
+            // only workspace-level pages become trees.
+            "page" if result.parent.parent_type == "workspace" => ("page", result.id.as_str()),
+            "data_source" => match result.database_id.as_deref() {
+                Some(database_id) => ("database", database_id),
+                None => continue,
+            },
+            _ => continue,
+        };
+        let Some(id) = normalize_id(id) else {
+            continue;
+        };
+        if !known.insert(id.clone()) {
+            continue;
+        }
+        match init_discovered(state, discovery, kind, &id, result.title.as_deref()).await {
+            Ok(database) => {
+                info!("discovered {} {} ({})", kind, id, result.title.as_deref().unwrap_or(""));
+                discovered.push(database);
+            }
+            Err(err) => warn!("failed to set up discovered {} {}: {err:#}", kind, id),
+        }
+    }
+    if discovered.is_empty() {
+        return;
+    }
+
+    let mut databases = state
+        .databases
+        .write()
+        .unwrap_or_else(|err| err.into_inner());
+    let mut updated = databases.as_ref().clone();
+    updated.extend(discovered);
+    *databases = Arc::new(updated);
+}
+
+async fn init_discovered(
+    state: &AppState,
+    discovery: &Discovery,
+    kind: &str,
+    id: &str,
+    title: Option<&str>,
+) -> anyhow::Result<DatabaseState> {
+    let storage = discovery
+        .config
+        .storage
+        .iter()
+        .map(|template| render_storage(template, id, title.unwrap_or_default()))
+        .collect::<Vec<_>>();
+    if kind == "page" {
+        let page = PageTreeConfig {
+            id: id.to_string(),
+            storage,
+            ..Default::default()
+        };
+        init_page_tree(&page, &discovery.sync).await
+    } else {
+        let db = DatabaseConfig {
+            id: id.to_string(),
+            storage,
+            ..Default::default()
+        };
+        init_database(&db, &discovery.sync, &state.notion, state.sqlite.as_ref()).await
+    }
+}
+
+fn render_storage(template: &BackendConfig, id: &str, title: &str) -> BackendConfig {
+    let slug = Some(slugify(title))
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| id.to_string());
+    let title = Some(title.trim().replace('/', "-"))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| id.to_string());
+    let mut storage = template.clone();
+    for value in storage.settings.values_mut() {
+        if let Value::String(text) = value {
+            *text = text
+                .replace("{{id}}", id)
+                .replace("{{slug:title}}", &slug)
+                .replace("{{title}}", &title);
+        }
+    }
+    storage
+}
+
+/// Case-insensitive match where `*` is any run of characters and `?` any
+/// single character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_literals_ignore_case() {
+        assert!(glob_match("Blog", "blog"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("Blog", "Blogs"));
+        assert!(!glob_match("", "Blog"));
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("Blog*", "Blog posts"));
+        assert!(glob_match("Blog*", "Blog"));
+        assert!(glob_match("*posts", "Blog posts"));
+        assert!(glob_match("*og*st*", "Blog posts"));
+        assert!(glob_match("B?og", "Blog"));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("B?og", "Bog"));
+        assert!(!glob_match("*posts", "Blog post"));
+        assert!(!glob_match("Blog*", "My Blog"));
+    }
+
+    #[test]
+    fn glob_match_backtracks_over_repeated_prefixes() {
+        assert!(glob_match("*ab", "aaab"));
+        assert!(glob_match("a*a*b", "aaaab"));
+        assert!(!glob_match("a*a*c", "aaaab"));
+        assert!(glob_match("笔记*", "笔记 2024"));
+    }
+}
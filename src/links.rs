@@ -0,0 +1,92 @@
+use std::collections::{BTreeMap, HashSet};
+
+use log::{info, warn};
+
+use crate::manifest::PageState;
+use crate::render::{LinkResolver, LinkTarget};
+use crate::storage::StorageBackend;
+use crate::{AppState, DatabaseState};
+
+pub struct ManifestLinks {
+    storage: Vec<StorageBackend>,
+}
+
+impl ManifestLinks {
+    pub fn new(state: &AppState, database: &DatabaseState) -> Self {
+        let backends = database
+            .storage
+            .iter()
+            .map(|storage| storage.name.as_str())
+            .collect::<HashSet<_>>();
+        let storage = state
+            .databases()
+            .iter()
+            .filter(|other| {
+                other.id == database.id
+                    || other
+                        .storage
+                        .iter()
+                        .any(|storage| backends.contains(storage.name.as_str()))
+            })
+            .flat_map(|other| other.storage.iter().cloned())
+            .collect();
+        Self { storage }
+    }
+
+    pub fn links_changed(&self, page: &PageState) -> bool {
+        page.links.iter().any(|(target, recorded)| {
+            self.resolve(target).map(|target| target.path) != *recorded
+        })
+    }
+}
+
+impl LinkResolver for ManifestLinks {
+    fn resolve(&self, page_id: &str) -> Option<LinkTarget> {
+        self.storage
+            .iter()
+            .find_map(|storage| {
+                let manifest = storage.manifest();
+                manifest.pages.get(page_id).map(|page| LinkTarget {
+                    path: page.path(page_id),
+                    title: page.title.clone(),
+                })
+            })
+    }
+}
+
+pub fn report_unresolved_links(state: &AppState) {
+    let mut unresolved: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for database in state.databases().iter() {
+        for storage in &database.storage {
+            let manifest = storage.manifest();
+            for (page_id, page) in &manifest.pages {
+                let targets = page
+                    .links
+                    .iter()
+                    .filter(|(_, path)| path.is_none())
+                    .map(|(target, _)| target.clone());
+                let entry = unresolved.entry(page_id.clone()).or_default();
+                for target in targets {
+                    if !entry.contains(&target) {
+                        entry.push(target);
+                    }
+                }
+            }
+        }
+    }
+    unresolved.retain(|_, targets| !targets.is_empty());
+
+    if unresolved.is_empty() {
+        info!("all internal links resolved");
+        return;
+    }
+    let total = unresolved.values().map(Vec::len).sum::<usize>();
+    warn!(
+        "{} internal links in {} pages could not be resolved and point to Notion",
+        total,
+        unresolved.len()
+    );
+    for (page_id, targets) in unresolved {
+        warn!("unresolved links in page {}: {}", page_id, targets.join(", "));
+    }
+}
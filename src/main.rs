@@ -1,94 +1,288 @@
 use anyhow::{Context, Result};
 use axum::{routing::{get, post}, Router};
+use clap::{Args, Parser, Subcommand};
+use opendal::ErrorKind;
 use tokio::net::TcpListener;
-use log::info;
+use log::{info, warn};
 use logforth::append;
 use logforth::layout::TextLayout;
 use logforth::record::{Level, LevelFilter};
-use std::collections::HashSet;
-
-const DEFAULT_MAX_DEPTH: usize = 3;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::{Arc, RwLock};
 
 mod config;
+mod discovery;
+mod links;
+mod manifest;
 mod notion;
+mod plan;
 mod render;
 mod scheduler;
+mod schema;
+mod sqlite;
 mod storage;
 mod sync;
+mod table;
+mod template;
+mod transform;
+mod tree;
 mod webhook;
 
-use config::AppConfig;
+use config::{
+    AppConfig, DatabaseConfig, DeletedPagesMode, FormatConfig, MaxDepth, PageTreeConfig,
+    PropertyTransform, RenderConfig, SyncConfig, TableFormat,
+};
+use discovery::Discovery;
 use notion::{DataSourceInfo, NotionClient};
+use plan::DryRun;
 use scheduler::spawn_periodic_sync;
-use storage::init_opendal;
+use sqlite::{RowTable, SqliteExport};
+use render::OutputTarget;
+use storage::{init_storage, StorageBackend};
+use template::PathTemplate;
+use tree::PageTree;
 use webhook::handle_webhook;
 
 #[derive(Clone)]
 pub struct AppState {
     pub notion: NotionClient,
-    pub max_depth: usize,
+    pub deleted_pages: DeletedPagesMode,
     pub webhook_secret: Option<String>,
     pub webhook_max_age_seconds: u64,
-    pub databases: Vec<DatabaseState>,
+    pub databases: Arc<RwLock<Arc<Vec<DatabaseState>>>>,
+    pub discovery: Option<Discovery>,
+    pub dry_run: Option<Arc<DryRun>>,
     pub http: reqwest::Client,
+    pub sqlite: Option<SqliteExport>,
+}
+
+impl AppState {
+    /// Snapshot of the configured and discovered databases and page trees.
+    pub fn databases(&self) -> Arc<Vec<DatabaseState>> {
+        self.databases
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
 }
 
 #[derive(Clone)]
 pub struct DatabaseState {
     pub id: String,
-    pub op: opendal::Operator,
+    pub storage: Vec<StorageBackend>,
     pub data_sources: Vec<DataSourceInfo>,
     pub property_map: std::collections::BTreeMap<String, String>,
     pub property_includes: Option<HashSet<String>>,
+    pub property_transforms: std::collections::BTreeMap<String, PropertyTransform>,
+    pub concurrency: usize,
+    pub max_depth: MaxDepth,
+    pub render: RenderConfig,
+    pub path_template: PathTemplate,
+    pub outputs: Vec<OutputTarget>,
+    pub tables: Vec<TableFormat>,
+    pub tree: Option<PageTree>,
+    pub child_databases: bool,
+    /// Hash of the settings that shape rendered pages, see `config_hash`.
+    pub config_hash: String,
+}
+
+#[derive(Parser)]
+#[command(version, about = "Sync Notion databases and pages to storage")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the webhook server and periodic sync (the default)
+    Serve,
+    /// Sync every database and page tree once, exiting non-zero on failures
+    Sync {
+        #[command(flatten)]
+        dry_run: DryRunArgs,
+    },
+    /// Sync a single page by id or URL
+    SyncPage {
+        id: String,
+        #[command(flatten)]
+        dry_run: DryRunArgs,
+    },
+    /// Print a page as Markdown without writing to storage
+    Render { id: String },
+    /// Load the configuration and check every database, page tree root and
+    /// storage backend is reachable
+    CheckConfig,
+    /// List configured and discovered databases and page trees
+    ListDatabases,
+}
+
+#[derive(Args)]
+struct DryRunArgs {
+    /// Fetch and render everything, but only report what would change
+    #[arg(long)]
+    dry_run: bool,
+    /// Print the dry-run report as JSON
+    #[arg(long, requires = "dry_run")]
+    json: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_logging();
+    let command = Cli::parse().command.unwrap_or(Command::Serve);
+    let dry_run = match &command {
+        Command::Sync { dry_run } | Command::SyncPage { dry_run, .. } => dry_run.dry_run,
+        _ => false,
+    };
+    // Keep stdout clean for commands that print results.
+    init_logging(dry_run || matches!(command, Command::Render { .. } | Command::ListDatabases));
     info!("logging initialized");
 
     let config = AppConfig::load()?;
     info!("configuration loaded");
+    // Only commands that sync pages keep the SQLite mirror up to date.
+    let mirror = !dry_run
+        && matches!(
+            command,
+            Command::Serve | Command::Sync { .. } | Command::SyncPage { .. }
+        );
+    let state = init_state(&config, dry_run, mirror).await?;
+
+    match command {
+        Command::Serve => serve(state, &config).await,
+        Command::Sync { dry_run } => {
+            let result = sync::sync_all(&state).await;
+            print_plan(&state, &dry_run)?;
+            result
+        }
+        Command::SyncPage { id, dry_run: args } => {
+            let page_id = parse_page_id(&id)?;
+            let result = sync::sync_page_by_id(&state, &page_id).await;
+            print_plan(&state, &args)?;
+            if !result? {
+                return Err(anyhow::anyhow!(
+                    "page {} is not in any configured database or page tree",
+                    page_id
+                ));
+            }
+            Ok(())
+        }
+        Command::Render { id } => {
+            print!("{}", sync::render_page(&state, &parse_page_id(&id)?).await?);
+            Ok(())
+        }
+        Command::CheckConfig => check_config(&state).await,
+        Command::ListDatabases => {
+            if let Some(discovery) = state.discovery.as_ref() {
+                discovery::discover(&state, discovery).await;
+            }
+            for database in state.databases().iter() {
+                let kind = if database.tree.is_some() { "page" } else { "database" };
+                let storage = database
+                    .storage
+                    .iter()
+                    .map(|storage| storage.name.as_str())
+                    .collect::<Vec<_>>();
+                println!("{}\t{}\t{}", kind, database.id, storage.join(", "));
+                for data_source in &database.data_sources {
+                    println!(
+                        "\tdata source\t{}\t{}",
+                        data_source.id,
+                        data_source.name.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn init_state(config: &AppConfig, dry_run: bool, mirror: bool) -> Result<AppState> {
     let notion = NotionClient::new(&config.notion.api_key)?;
     let http = reqwest::Client::new();
+    let sqlite = config
+        .sqlite
+        .as_ref()
+        .filter(|_| mirror)
+        .map(|sqlite| SqliteExport::open(&sqlite.path))
+        .transpose()?;
     let mut databases = Vec::new();
     for db in &config.database {
-        let backend = db
-            .storage
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("database {} has no storage", db.id))?;
-        let op = init_opendal(backend)?;
-        let data_sources = notion.fetch_database_data_sources(&db.id).await?;
-        let property_map = if db.properties.map.is_empty() {
-            db.key_map.clone()
-        } else {
-            db.properties.map.clone()
-        };
-        let property_includes = db
-            .properties
-            .filter
-            .includes
-            .as_ref()
-            .map(|items| items.iter().cloned().collect());
-        databases.push(DatabaseState {
-            id: db.id.clone(),
-            op,
-            data_sources,
-            property_map,
-            property_includes,
-        });
+        databases.push(init_database(db, &config.sync, &notion, sqlite.as_ref()).await?);
+    }
+    for page in &config.page {
+        databases.push(init_page_tree(page, &config.sync).await?);
     }
     info!("databases initialized");
 
-    let state = AppState {
+    let discovery = config.discovery.clone().map(|discovery| Discovery {
+        config: discovery,
+        sync: config.sync.clone(),
+    });
+    Ok(AppState {
         notion,
-        max_depth: DEFAULT_MAX_DEPTH,
-        webhook_secret: config.webhook.secret,
+        deleted_pages: config.sync.deleted_pages,
+        webhook_secret: config.webhook.secret.clone(),
         webhook_max_age_seconds: config.webhook.max_age_seconds,
-        databases,
+        databases: Arc::new(RwLock::new(Arc::new(databases))),
+        discovery,
+        dry_run: dry_run.then(Default::default),
         http,
+        sqlite,
+    })
+}
+
+/// Fetches every page tree root and stats every storage backend on top of
+/// what loading the databases already checked.
+async fn check_config(state: &AppState) -> Result<()> {
+    let databases = state.databases();
+    let mut failed = 0usize;
+    for database in databases.iter() {
+        if let Some(tree) = database.tree.as_ref()
+            && let Err(err) = state.notion.get_page_metadata(&tree.root).await
+        {
+            warn!("page {} is not reachable: {err:#}", tree.root);
+            failed += 1;
+        }
+        for storage in &database.storage {
+            match storage.op.stat(&storage.manifest_path).await {
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => {
+                    warn!(
+                        "storage {} of {} is not reachable: {err}",
+                        storage.name, database.id
+                    );
+                    failed += 1;
+                }
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} configuration checks failed", failed));
+    }
+    let trees = databases.iter().filter(|db| db.tree.is_some()).count();
+    println!(
+        "configuration ok: {} databases, {} page trees",
+        databases.len() - trees,
+        trees
+    );
+    Ok(())
+}
+
+fn print_plan(state: &AppState, args: &DryRunArgs) -> Result<()> {
+    let Some(plan) = state.dry_run.as_ref() else {
+        return Ok(());
     };
+    if args.json {
+        println!("{}", plan.report_json()?);
+    } else {
+        print!("{}", plan.report());
+    }
+    Ok(())
+}
 
+async fn serve(state: AppState, config: &AppConfig) -> Result<()> {
     spawn_periodic_sync(state.clone(), config.sync.interval_seconds);
     info!("periodic sync started");
 
@@ -107,11 +301,165 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+fn parse_page_id(value: &str) -> Result<String> {
+    notion::normalize_id(value)
+        .or_else(|| notion::page_id_from_url(value))
+        .ok_or_else(|| anyhow::anyhow!("{} is not a Notion page id or URL", value))
+}
+
+pub async fn init_database(
+    db: &DatabaseConfig,
+    sync: &SyncConfig,
+    notion: &NotionClient,
+    sqlite: Option<&SqliteExport>,
+) -> Result<DatabaseState> {
+    if db.storage.is_empty() {
+        return Err(anyhow::anyhow!("database {} has no storage", db.id));
+    }
+    let path_template = match db.path_template.as_deref() {
+        Some(template) => PathTemplate::parse(template)?,
+        None => PathTemplate::default(),
+    };
+    let outputs = if db.format.is_empty() {
+        vec![OutputTarget::default()]
+    } else {
+        db.format.iter().map(OutputTarget::new).collect()
+    };
+    let data_sources = notion.fetch_database_data_sources(&db.id).await?;
+    let data_source_ids = data_sources
+        .iter()
+        .map(|data_source| data_source.id.clone())
+        .collect::<Vec<_>>();
+    let storage = init_storage(&db.storage, &db.id, &data_source_ids).await?;
+    let property_map = if db.properties.map.is_empty() {
+        db.key_map.clone()
+    } else {
+        db.properties.map.clone()
+    };
+    schema::validate_config(db, &property_map, &data_sources);
+    let property_includes = db
+        .properties
+        .filter
+        .includes
+        .as_ref()
+        .map(|items| items.iter().cloned().collect());
+    let max_depth = db.max_depth.unwrap_or(sync.max_depth);
+    let config_hash = config_hash(
+        &db.render,
+        &db.format,
+        &property_map,
+        property_includes.as_ref(),
+        &db.properties.transform,
+        max_depth,
+        db.child_databases,
+    );
+    if let Some(sqlite) = sqlite {
+        let tables = data_sources
+            .iter()
+            .map(|data_source| {
+                RowTable::new(data_source, &property_map, property_includes.as_ref())
+            })
+            .collect();
+        sqlite.register_data_sources(&db.id, tables).await?;
+    }
+    Ok(DatabaseState {
+        id: db.id.clone(),
+        storage,
+        data_sources,
+        property_map,
+        property_includes,
+        property_transforms: db.properties.transform.clone(),
+        concurrency: db.concurrency.unwrap_or(sync.concurrency).max(1),
+        max_depth,
+        render: db.render.clone(),
+        path_template,
+        outputs,
+        tables: db.tables.clone(),
+        tree: None,
+        child_databases: db.child_databases,
+        config_hash,
+    })
+}
+
+pub async fn init_page_tree(page: &PageTreeConfig, sync: &SyncConfig) -> Result<DatabaseState> {
+    let root = notion::normalize_id(&page.id)
+        .ok_or_else(|| anyhow::anyhow!("page {} is not a valid page id", page.id))?;
+    let outputs = if page.format.is_empty() {
+        vec![OutputTarget::default()]
+    } else {
+        page.format.iter().map(OutputTarget::new).collect()
+    };
+    let max_depth = page.max_depth.unwrap_or(sync.max_depth);
+    let config_hash = config_hash(
+        &page.render,
+        &page.format,
+        &BTreeMap::new(),
+        None,
+        &BTreeMap::new(),
+        max_depth,
+        true,
+    );
+    Ok(DatabaseState {
+        id: root.clone(),
+        storage: init_storage(&page.storage, &root, &[]).await?,
+        data_sources: Vec::new(),
+        property_map: Default::default(),
+        property_includes: None,
+        property_transforms: Default::default(),
+        concurrency: page.concurrency.unwrap_or(sync.concurrency).max(1),
+        max_depth,
+        render: page.render.clone(),
+        path_template: PathTemplate::default(),
+        outputs,
+        tables: Vec::new(),
+        tree: Some(PageTree {
+            root,
+            directory: page.directory.clone().unwrap_or_default(),
+        }),
+        child_databases: true,
+        config_hash,
+    })
+}
+
+/// Hash of the settings that shape rendered pages. Manifests record it after
+/// a full scan, and a page rendered under other settings counts as stale.
+fn config_hash(
+    render: &RenderConfig,
+    format: &[FormatConfig],
+    property_map: &BTreeMap<String, String>,
+    property_includes: Option<&HashSet<String>>,
+    property_transforms: &BTreeMap<String, PropertyTransform>,
+    max_depth: MaxDepth,
+    child_databases: bool,
+) -> String {
+    let includes = property_includes.map(|includes| includes.iter().collect::<BTreeSet<_>>());
+    let settings = serde_json::json!({
+        "render": render,
+        "format": format,
+        "map": property_map,
+        "includes": includes,
+        "transform": property_transforms,
+        "max_depth": format!("{:?}", max_depth),
+        // Inline database indexes are linked from their parent pages.
+        "child_databases": child_databases,
+    });
+    manifest::content_hash(&settings.to_string())
+}
+
 async fn health() -> &'static str {
     "ok"
 }
 
-fn init_logging() {
+fn init_logging(stderr_only: bool) {
+    if stderr_only {
+        logforth::starter_log::builder()
+            .dispatch(|d| {
+                d.filter(LevelFilter::MoreSevereEqual(Level::Info))
+                    .append(append::Stderr::default().with_layout(TextLayout::default()))
+            })
+            .apply();
+        return;
+    }
     logforth::starter_log::builder()
         .dispatch(|d| {
             d.filter(LevelFilter::MoreSevereEqual(Level::Error))
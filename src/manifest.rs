@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use opendal::{ErrorKind, Operator};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use crate::notion::normalize_id;
+
+/// The manifest every database on a backend shared before each got its own.
+/// It is only read, to seed a database's manifest the first time.
+pub const LEGACY_MANIFEST_PATH: &str = ".notion-sync/state.json";
+
+/// Where a database or page tree keeps its manifest, so several of them can
+/// share a backend without overwriting each other's state.
+pub fn manifest_path(database_id: &str) -> String {
+    let id = normalize_id(database_id).unwrap_or_else(|| database_id.to_string());
+    format!(".notion-sync/{}/state.json", id)
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SyncManifest {
+    #[serde(default)]
+    pub data_sources: BTreeMap<String, DataSourceState>,
+    #[serde(default)]
+    pub pages: BTreeMap<String, PageState>,
+    /// Render settings hash of the last full scan, see `DatabaseState`.
+    #[serde(default)]
+    pub config_hash: Option<String>,
+    /// Page ids keyed by the path they reserved in this process, so pages
+    /// that render to the same path don't write over each other before
+    /// either is recorded.
+    #[serde(skip)]
+    pub claims: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DataSourceState {
+    pub last_synced_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PageState {
+    pub last_edited_time: String,
+    pub content_hash: String,
+    #[serde(default)]
+    pub data_source_id: Option<String>,
+    #[serde(default)]
+    pub blobs: Vec<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub links: BTreeMap<String, Option<String>>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub created_time: Option<String>,
+    #[serde(default)]
+    pub properties: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub child_pages: Vec<String>,
+    #[serde(default)]
+    pub child_databases: Vec<(String, String)>,
+    /// Id of the database or page tree that synced the page.
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+impl PageState {
+    pub fn path(&self, page_id: &str) -> String {
+        self.path
+            .clone()
+            .unwrap_or_else(|| format!("pages/{}.md", page_id))
+    }
+}
+
+impl SyncManifest {
+    pub fn is_page_current(&self, page_id: &str, last_edited_time: &str) -> bool {
+        self.pages
+            .get(page_id)
+            .is_some_and(|page| page.last_edited_time == last_edited_time)
+    }
+
+    pub fn is_content_current(&self, page_id: &str, content_hash: &str) -> bool {
+        self.pages
+            .get(page_id)
+            .is_some_and(|page| page.content_hash == content_hash)
+    }
+
+    /// The page other than `page_id` holding `path`: the one recorded there
+    /// by an earlier sync, or else the one that reserved it in this process.
+    pub fn path_holder(&self, path: &str, page_id: &str) -> Option<String> {
+        let recorded = self
+            .pages
+            .iter()
+            .find(|(id, page)| *id != page_id && page.path(id) == path)
+            .map(|(id, _)| id.clone());
+        recorded.or_else(|| {
+            self.claims
+                .get(path)
+                .filter(|holder| *holder != page_id)
+                .cloned()
+        })
+    }
+
+    pub fn is_config_current(&self, config_hash: &str) -> bool {
+        self.config_hash.as_deref() == Some(config_hash)
+    }
+
+    pub fn last_synced_at(&self, data_source_id: &str) -> Option<&str> {
+        self.data_sources
+            .get(data_source_id)
+            .map(|state| state.last_synced_at.as_str())
+    }
+}
+
+pub async fn load_manifest(op: &Operator, path: &str) -> Result<Option<SyncManifest>> {
+    match op.read(path).await {
+        Ok(buffer) => serde_json::from_slice(&buffer.to_vec())
+            .map(Some)
+            .with_context(|| format!("failed to parse {path}")),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("failed to read {path}")),
+    }
+}
+
+/// The part of the shared legacy manifest that belongs to a database with
+/// the given data sources. Page trees own nothing in it and start fresh.
+pub async fn load_legacy_manifest(
+    op: &Operator,
+    data_source_ids: &[String],
+) -> Result<SyncManifest> {
+    let Some(mut manifest) = load_manifest(op, LEGACY_MANIFEST_PATH).await? else {
+        return Ok(SyncManifest::default());
+    };
+    let owned = |id: Option<&str>| {
+        id.is_some_and(|id| data_source_ids.iter().any(|own| own == id))
+    };
+    manifest.pages.retain(|_, page| owned(page.data_source_id.as_deref()));
+    manifest.data_sources.retain(|id, _| owned(Some(id)));
+    manifest.config_hash = None;
+    Ok(manifest)
+}
+
+pub async fn save_manifest(op: &Operator, path: &str, manifest: &SyncManifest) -> Result<()> {
+    let body = serde_json::to_vec_pretty(manifest)?;
+    op.write(path, body)
+        .await
+        .with_context(|| format!("failed to write {path}"))?;
+    Ok(())
+}
+
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_page_keeps_its_path() {
+        let mut manifest = SyncManifest::default();
+        let mut recorded = page(None);
+        recorded.path = Some("notes.md".to_string());
+        manifest.pages.insert("b".to_string(), recorded);
+        manifest.claims.insert("todo.md".to_string(), "a".to_string());
+
+        assert_eq!(manifest.path_holder("notes.md", "a"), Some("b".to_string()));
+        assert_eq!(manifest.path_holder("notes.md", "b"), None);
+        assert_eq!(manifest.path_holder("todo.md", "c"), Some("a".to_string()));
+        assert_eq!(manifest.path_holder("todo.md", "a"), None);
+        assert_eq!(manifest.path_holder("other.md", "a"), None);
+    }
+
+    fn page(data_source_id: Option<&str>) -> PageState {
+        serde_json::from_value(serde_json::json!({
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "content_hash": "",
+            "data_source_id": data_source_id,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn legacy_manifest_seeds_only_owned_pages() {
+        let op = Operator::new(opendal::services::Memory::default())
+            .unwrap()
+            .finish();
+        let mut legacy = SyncManifest::default();
+        legacy.pages.insert("a".to_string(), page(Some("ds-1")));
+        legacy.pages.insert("b".to_string(), page(Some("ds-2")));
+        legacy.pages.insert("tree".to_string(), page(None));
+        save_manifest(&op, LEGACY_MANIFEST_PATH, &legacy).await.unwrap();
+
+        let owned = load_legacy_manifest(&op, &["ds-1".to_string()]).await.unwrap();
+        assert_eq!(owned.pages.keys().collect::<Vec<_>>(), vec!["a"]);
+        let tree = load_legacy_manifest(&op, &[]).await.unwrap();
+        assert!(tree.pages.is_empty());
+
+        let path = manifest_path("0123456789abcdef0123456789ABCDEF");
+        assert_eq!(path, ".notion-sync/01234567-89ab-cdef-0123-456789abcdef/state.json");
+        assert!(load_manifest(&op, &path).await.unwrap().is_none());
+    }
+}
@@ -1,14 +1,57 @@
 use anyhow::{anyhow, Result};
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
-use serde::Deserialize;
+use futures::future::BoxFuture;
+use futures::{stream, StreamExt, TryStreamExt};
+use log::warn;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
 
 const NOTION_VERSION: &str = "2025-09-03";
+const REQUEST_INTERVAL: Duration = Duration::from_millis(334);
+const MAX_RETRIES: u32 = 5;
+const CHILD_FETCH_CONCURRENCY: usize = 4;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
 
 #[derive(Clone)]
 pub struct NotionClient {
     client: reqwest::Client,
+    limiter: Arc<RateLimiter>,
+}
+
+struct RateLimiter {
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new() -> Self {
+        Self {
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + REQUEST_INTERVAL;
+            slot
+        };
+        sleep_until(slot).await;
+    }
+
+    async fn pause(&self, delay: Duration) {
+        let mut next_slot = self.next_slot.lock().await;
+        let resume = Instant::now() + delay;
+        if *next_slot < resume {
+            *next_slot = resume;
+        }
+    }
 }
 
 impl NotionClient {
@@ -24,36 +67,102 @@ impl NotionClient {
         let client = reqwest::Client::builder()
             .default_headers(headers)
             .build()?;
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            limiter: Arc::new(RateLimiter::new()),
+        })
     }
 
-    pub async fn fetch_blocks(&self, block_id: &str, depth: usize) -> Result<Vec<Block>> {
-        let mut blocks = self.fetch_block_children(block_id).await?;
-        if depth == 0 {
-            return Ok(blocks);
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let mut attempt = 0u32;
+        loop {
+            let pending = request
+                .try_clone()
+                .ok_or_else(|| anyhow!("Notion request cannot be retried"))?;
+            self.limiter.acquire().await;
+            let delay = match pending.send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = parse_retry_after(&response);
+                    let body = response.text().await.unwrap_or_default();
+                    if !is_transient(status) || attempt >= MAX_RETRIES {
+                        return Err(anyhow!("Notion API error {status}: {body}"));
+                    }
+                    let delay = retry_after.unwrap_or_else(|| backoff_delay(attempt));
+                    if status == StatusCode::TOO_MANY_REQUESTS {
+                        self.limiter.pause(delay).await;
+                    }
+                    warn!(
+                        "Notion API error {status}, retrying in {:.1}s (attempt {}/{})",
+                        delay.as_secs_f64(),
+                        attempt + 1,
+                        MAX_RETRIES
+                    );
+                    delay
+                }
+                Err(err) if (err.is_timeout() || err.is_connect()) && attempt < MAX_RETRIES => {
+                    let delay = backoff_delay(attempt);
+                    warn!(
+                        "Notion request failed: {err}, retrying in {:.1}s (attempt {}/{})",
+                        delay.as_secs_f64(),
+                        attempt + 1,
+                        MAX_RETRIES
+                    );
+                    delay
+                }
+                Err(err) => return Err(err.into()),
+            };
+            sleep(delay).await;
+            attempt += 1;
         }
+    }
 
-        let mut depths = vec![depth; blocks.len()];
-        let mut index = 0usize;
-        while index < blocks.len() {
-            let remaining_depth = depths[index];
-            if remaining_depth > 0 && blocks[index].has_children {
-                let id = blocks[index].id.clone();
-                let marker = Block::children_marker(&id);
-                blocks.insert(index + 1, marker);
-                depths.insert(index + 1, 0);
-
-                let children = self.fetch_block_children(&id).await?;
-                let child_depth = remaining_depth.saturating_sub(1);
-                for (offset, child) in children.into_iter().enumerate() {
-                    blocks.insert(index + 2 + offset, child);
-                    depths.insert(index + 2 + offset, child_depth);
+    pub fn fetch_blocks<'a>(
+        &'a self,
+        block_id: &'a str,
+        depth: Option<usize>,
+    ) -> BoxFuture<'a, Result<Vec<Block>>> {
+        Box::pin(async move {
+            let mut children = self.fetch_block_children(block_id).await?;
+            if depth == Some(0) {
+                for block in children.iter_mut() {
+                    block.truncated = block.has_children && !block.is_child_document();
                 }
+                return Ok(children);
             }
-            index += 1;
-        }
+            let child_depth = depth.map(|depth| depth - 1);
 
-        Ok(blocks)
+            // Sub-pages and inline databases are separate documents; their
+            // content is never part of this block tree.
+            let parent_ids = children
+                .iter()
+                .filter(|block| block.has_children && !block.is_child_document())
+                .map(|block| (block.id.clone(), block.children_source_id().to_string()))
+                .collect::<Vec<_>>();
+            let nested = stream::iter(parent_ids)
+                .map(|(id, source_id)| async move {
+                    match self.fetch_blocks(&source_id, child_depth).await {
+                        Err(err) if source_id != id => {
+                            warn!("failed to fetch synced block {source_id} for {id}: {err}");
+                            Ok(Vec::new())
+                        }
+                        result => result,
+                    }
+                })
+                .buffered(CHILD_FETCH_CONCURRENCY)
+                .try_collect::<Vec<_>>()
+                .await?;
+
+            let mut nested = nested.into_iter();
+            for block in children
+                .iter_mut()
+                .filter(|block| block.has_children && !block.is_child_document())
+            {
+                block.children = nested.next().unwrap_or_default();
+            }
+            Ok(children)
+        })
     }
 
     async fn fetch_block_children(&self, block_id: &str) -> Result<Vec<Block>> {
@@ -69,14 +178,11 @@ impl NotionClient {
                     .map(|value| format!("&start_cursor={}", value))
                     .unwrap_or_default()
             );
-            let response = self.client.get(&url).send().await?;
-            let status = response.status();
-            if !status.is_success() {
-                let body = response.text().await.unwrap_or_default();
-                return Err(anyhow!("Notion API error {status}: {body}"));
-            }
+            let response = self.send(self.client.get(&url)).await?;
             let data: BlocksResponse = response.json().await?;
-            blocks.extend(data.results);
+            for raw in data.results {
+                blocks.push(Block::from_raw(raw)?);
+            }
             if data.has_more {
                 cursor = data.next_cursor;
             } else {
@@ -91,28 +197,33 @@ impl NotionClient {
         let data_sources = self.fetch_database_data_sources(database_id).await?;
         let mut page_ids = Vec::new();
         for data_source in data_sources {
-            let mut ids = self.query_data_source_page_ids(&data_source.id).await?;
+            let mut ids = self.query_data_source_page_ids(&data_source.id, None).await?;
             page_ids.append(&mut ids);
         }
         Ok(page_ids)
     }
 
-    pub async fn query_data_source_page_ids(&self, data_source_id: &str) -> Result<Vec<String>> {
+    pub async fn query_data_source_page_ids(
+        &self,
+        data_source_id: &str,
+        edited_since: Option<&str>,
+    ) -> Result<Vec<String>> {
         let mut page_ids = Vec::new();
         let mut cursor: Option<String> = None;
 
         loop {
             let url = format!("https://api.notion.com/v1/data_sources/{}/query", data_source_id);
             let mut body = json!({});
+            if let Some(since) = edited_since {
+                body["filter"] = json!({
+                    "timestamp": "last_edited_time",
+                    "last_edited_time": { "on_or_after": since },
+                });
+            }
             if let Some(value) = cursor.as_ref() {
                 body["start_cursor"] = json!(value);
             }
-            let response = self.client.post(&url).json(&body).send().await?;
-            let status = response.status();
-            if !status.is_success() {
-                let body = response.text().await.unwrap_or_default();
-                return Err(anyhow!("Notion API error {status}: {body}"));
-            }
+            let response = self.send(self.client.post(&url).json(&body)).await?;
             let data: DataSourceQueryResponse = response.json().await?;
             page_ids.extend(data.results.into_iter().map(|page| page.id));
             if data.has_more {
@@ -130,60 +241,291 @@ impl NotionClient {
         database_id: &str,
     ) -> Result<Vec<DataSourceInfo>> {
         let url = format!("https://api.notion.com/v1/databases/{}", database_id);
-        let response = self.client.get(&url).send().await?;
-        let status = response.status();
-        if !status.is_success() {
-            let body = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Notion API error {status}: {body}"));
-        }
+        let response = self.send(self.client.get(&url)).await?;
         let data: DatabaseResponse = response.json().await?;
-        Ok(data.data_sources)
+        let mut data_sources = data.data_sources;
+        for data_source in data_sources.iter_mut() {
+            match self.fetch_data_source_schema(&data_source.id).await {
+                Ok(schema) => data_source.schema = Some(schema),
+                Err(err) => warn!(
+                    "failed to fetch schema for data source {}: {err}",
+                    data_source.id
+                ),
+            }
+        }
+        Ok(data_sources)
+    }
+
+    pub async fn fetch_data_source_schema(&self, data_source_id: &str) -> Result<DataSourceSchema> {
+        let url = format!("https://api.notion.com/v1/data_sources/{}", data_source_id);
+        let response = self.send(self.client.get(&url)).await?;
+        let data: DataSourceResponse = response.json().await?;
+        let name = data
+            .title
+            .iter()
+            .map(|item| item.plain_text.as_str())
+            .collect::<String>();
+        Ok(DataSourceSchema {
+            id: data.id,
+            name: (!name.is_empty()).then_some(name),
+            properties: data
+                .properties
+                .into_iter()
+                .map(|(name, property)| (name, PropertySchema::from_json(&property)))
+                .collect(),
+        })
+    }
+
+    /// Every page and data source shared with the integration.
+    pub async fn search(&self) -> Result<Vec<SearchResult>> {
+        let mut results = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut body = json!({ "page_size": 100 });
+            if let Some(value) = cursor.as_ref() {
+                body["start_cursor"] = json!(value);
+            }
+            let request = self.client.post("https://api.notion.com/v1/search").json(&body);
+            let response = self.send(request).await?;
+            let data: SearchResponse = response.json().await?;
+            results.extend(data.results.into_iter().map(SearchResult::from));
+            if data.has_more {
+                cursor = data.next_cursor;
+            } else {
+                break;
+            }
+        }
+
+        Ok(results)
     }
 
     pub async fn get_page_parent(&self, page_id: &str) -> Result<PageParent> {
         let url = format!("https://api.notion.com/v1/pages/{}", page_id);
-        let response = self.client.get(&url).send().await?;
-        let status = response.status();
-        if !status.is_success() {
-            let body = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Notion API error {status}: {body}"));
-        }
+        let response = self.send(self.client.get(&url)).await?;
         let data: PageResponse = response.json().await?;
         Ok(PageParent {
             parent_type: data.parent.parent_type,
             database_id: data.parent.database_id,
             data_source_id: data.parent.data_source_id,
+            page_id: data.parent.page_id,
         })
     }
 
     pub async fn get_page_metadata(&self, page_id: &str) -> Result<PageMetadata> {
         let url = format!("https://api.notion.com/v1/pages/{}", page_id);
-        let response = self.client.get(&url).send().await?;
-        let status = response.status();
-        if !status.is_success() {
-            let body = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Notion API error {status}: {body}"));
-        }
-        let data: PageResponse = response.json().await?;
+        let response = self.send(self.client.get(&url)).await?;
+        let mut data: PageResponse = response.json().await?;
+        self.complete_truncated_properties(&data.id, &mut data.properties)
+            .await;
         Ok(PageMetadata {
             id: data.id,
             url: data.url,
             created_time: data.created_time,
             last_edited_time: data.last_edited_time,
+            archived: data.archived || data.in_trash,
             title: extract_page_title(&data.properties),
             parent: PageParent {
                 parent_type: data.parent.parent_type,
                 database_id: data.parent.database_id,
                 data_source_id: data.parent.data_source_id,
+                page_id: data.parent.page_id,
             },
             properties: extract_page_properties(&data.properties),
+            raw_properties: data.properties,
         })
     }
+
+    async fn complete_truncated_properties(
+        &self,
+        page_id: &str,
+        properties: &mut serde_json::Value,
+    ) {
+        let Some(properties) = properties.as_object_mut() else {
+            return;
+        };
+        for (name, property) in properties.iter_mut() {
+            if property.get("has_more").and_then(|v| v.as_bool()) != Some(true) {
+                continue;
+            }
+            match self.fetch_property_items(page_id, property).await {
+                Ok(full) => *property = full,
+                Err(err) => warn!(
+                    "failed to fetch all values of {} on page {}: {err}",
+                    name, page_id
+                ),
+            }
+        }
+    }
+
+    /// Pages through the property item endpoint and rebuilds the property in
+    /// the shape the page object uses, with every item included.
+    async fn fetch_property_items(
+        &self,
+        page_id: &str,
+        property: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let property_id = property
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("property has no id"))?;
+        let prop_type = property
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let mut items = Vec::new();
+        let mut property_item;
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let url = format!(
+                "https://api.notion.com/v1/pages/{}/properties/{}{}",
+                page_id,
+                property_id,
+                cursor
+                    .as_ref()
+                    .map(|value| format!("?start_cursor={}", value))
+                    .unwrap_or_default()
+            );
+            let response = self.send(self.client.get(&url)).await?;
+            let data: PropertyItemsResponse = response.json().await?;
+            if data.object != "list" {
+                return Ok(property.clone());
+            }
+            items.extend(data.results);
+            property_item = data.property_item;
+            if data.has_more {
+                cursor = data.next_cursor;
+            } else {
+                break;
+            }
+        }
+
+        let mut full = property.clone();
+        full["has_more"] = json!(false);
+        if prop_type == "rollup" {
+            let rollup = property_item.get("rollup").cloned().unwrap_or(json!({}));
+            full["rollup"] = if rollup.get("type").and_then(|v| v.as_str()) == Some("array") {
+                let array = items.iter().map(page_item_value).collect::<Vec<_>>();
+                json!({ "type": "array", "array": array, "function": rollup.get("function") })
+            } else {
+                rollup
+            };
+        } else {
+            full[prop_type] = serde_json::Value::Array(
+                items
+                    .iter()
+                    .filter_map(|item| item.get(prop_type).cloned())
+                    .collect(),
+            );
+        }
+        Ok(full)
+    }
+}
+
+/// Property item types whose page-object payload is a list the API caps at
+/// 25 entries. Rollups carry a list of these nested under `array`.
+const LIST_PROPERTY_TYPES: [&str; 4] = ["title", "rich_text", "people", "relation"];
+
+#[derive(Debug, Deserialize)]
+struct PropertyItemsResponse {
+    object: String,
+    #[serde(default)]
+    results: Vec<serde_json::Value>,
+    #[serde(default)]
+    property_item: serde_json::Value,
+    next_cursor: Option<String>,
+    #[serde(default)]
+    has_more: bool,
+}
+
+/// Converts a property item into the `{type, <type>: ...}` value a rollup
+/// array holds, where list types are arrays rather than single items.
+fn page_item_value(item: &serde_json::Value) -> serde_json::Value {
+    let item_type = item.get("type").and_then(|v| v.as_str()).unwrap_or_default();
+    let value = item.get(item_type).cloned().unwrap_or_default();
+    let value = if LIST_PROPERTY_TYPES.contains(&item_type) && !value.is_array() {
+        json!([value])
+    } else {
+        value
+    };
+    json!({ "type": item_type, item_type: value })
+}
+
+pub fn normalize_id(value: &str) -> Option<String> {
+    let hex = value
+        .chars()
+        .filter(|c| *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
+
+pub fn page_id_from_url(url: &str) -> Option<String> {
+    let path = if let Some(path) = url.strip_prefix('/') {
+        path
+    } else {
+        let rest = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))?;
+        let (host, path) = rest.split_once('/')?;
+        if !(host.ends_with("notion.so") || host.ends_with("notion.site")) {
+            return None;
+        }
+        path
+    };
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let segment = path.rsplit('/').next().unwrap_or(path);
+    let tail = segment.rsplit('-').next().unwrap_or(segment);
+    normalize_id(tail).or_else(|| {
+        let start = segment.len().checked_sub(32)?;
+        normalize_id(segment.get(start..)?)
+    })
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn parse_retry_after(response: &Response) -> Option<Duration> {
+    retry_after_delay(response.headers().get(RETRY_AFTER)?.to_str().ok()?)
+}
+
+/// Delay from a `Retry-After` value in seconds, capped at `MAX_BACKOFF`
+/// before converting so huge values can't overflow a `Duration`.
+fn retry_after_delay(value: &str) -> Option<Duration> {
+    let seconds = value.trim().parse::<f64>().ok()?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::from_secs_f64(seconds.min(MAX_BACKOFF.as_secs_f64())))
+}
+
+fn backoff_delay(attempt: u32) -> Duration {
+    let base = BASE_BACKOFF.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_BACKOFF);
+    base.mul_f64(0.5 + fastrand::f64() * 0.5)
 }
 
 #[derive(Debug, Deserialize)]
 struct BlocksResponse {
-    results: Vec<Block>,
+    results: Vec<serde_json::Value>,
     next_cursor: Option<String>,
     has_more: bool,
 }
@@ -200,6 +542,65 @@ struct PageObject {
     id: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    results: Vec<SearchObject>,
+    next_cursor: Option<String>,
+    has_more: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchObject {
+    object: String,
+    id: String,
+    #[serde(default)]
+    title: Vec<RichText>,
+    #[serde(default)]
+    properties: serde_json::Value,
+    parent: Parent,
+    #[serde(default)]
+    database_parent: Option<Parent>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    /// `page` or `data_source`.
+    pub object: String,
+    pub id: String,
+    pub title: Option<String>,
+    /// The database a data source belongs to.
+    pub database_id: Option<String>,
+    /// Where the page, or a data source's database, lives.
+    pub parent: PageParent,
+}
+
+impl From<SearchObject> for SearchResult {
+    fn from(data: SearchObject) -> Self {
+        let title = if data.object == "page" {
+            extract_page_title(&data.properties)
+        } else {
+            Some(data.title.iter().map(|item| item.plain_text.as_str()).collect::<String>())
+                .filter(|title| !title.is_empty())
+        };
+        let (database_id, parent) = match data.database_parent {
+            Some(database_parent) => (data.parent.database_id, database_parent),
+            None => (None, data.parent),
+        };
+        Self {
+            object: data.object,
+            id: data.id,
+            title,
+            database_id,
+            parent: PageParent {
+                parent_type: parent.parent_type,
+                database_id: parent.database_id,
+                data_source_id: parent.data_source_id,
+                page_id: parent.page_id,
+            },
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct DatabaseResponse {
     data_sources: Vec<DataSourceInfo>,
@@ -210,6 +611,89 @@ pub struct DataSourceInfo {
     pub id: String,
     #[serde(default)]
     pub name: Option<String>,
+    #[serde(skip)]
+    pub schema: Option<DataSourceSchema>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataSourceResponse {
+    id: String,
+    #[serde(default)]
+    title: Vec<RichText>,
+    #[serde(default)]
+    properties: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DataSourceSchema {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub properties: BTreeMap<String, PropertySchema>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PropertySchema {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub property_type: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<SelectOption>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relation: Option<RelationTarget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SelectOption {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RelationTarget {
+    #[serde(default)]
+    pub data_source_id: Option<String>,
+    #[serde(default)]
+    pub database_id: Option<String>,
+}
+
+impl PropertySchema {
+    fn from_json(value: &serde_json::Value) -> Self {
+        let property_type = value
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let config = value.get(&property_type);
+        let options = config
+            .and_then(|config| config.get("options"))
+            .and_then(|options| serde_json::from_value(options.clone()).ok())
+            .unwrap_or_default();
+        let relation = config
+            .filter(|_| property_type == "relation")
+            .and_then(|config| serde_json::from_value(config.clone()).ok());
+        let expression = config
+            .filter(|_| property_type == "formula")
+            .and_then(|config| config.get("expression"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string());
+        Self {
+            id: value
+                .get("id")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            property_type,
+            options,
+            relation,
+            expression,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -218,6 +702,10 @@ struct PageResponse {
     url: String,
     created_time: String,
     last_edited_time: String,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    in_trash: bool,
     properties: serde_json::Value,
     parent: Parent,
 }
@@ -228,6 +716,8 @@ struct Parent {
     parent_type: String,
     database_id: Option<String>,
     data_source_id: Option<String>,
+    #[serde(default)]
+    page_id: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -235,6 +725,7 @@ pub struct PageParent {
     pub parent_type: String,
     pub database_id: Option<String>,
     pub data_source_id: Option<String>,
+    pub page_id: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -243,9 +734,11 @@ pub struct PageMetadata {
     pub url: String,
     pub created_time: String,
     pub last_edited_time: String,
+    pub archived: bool,
     pub title: Option<String>,
     pub parent: PageParent,
     pub properties: BTreeMap<String, PropertyValue>,
+    pub raw_properties: serde_json::Value,
 }
 
 fn extract_page_title(properties: &serde_json::Value) -> Option<String> {
@@ -268,10 +761,62 @@ fn extract_page_title(properties: &serde_json::Value) -> Option<String> {
     None
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PropertyValue {
     Text(String),
+    Number(f64),
+    Bool(bool),
+    Date {
+        start: String,
+        end: Option<String>,
+        time_zone: Option<String>,
+    },
     List(Vec<String>),
+    Object(serde_json::Value),
+    Null,
+}
+
+impl PropertyValue {
+    pub fn from_json(value: &serde_json::Value) -> Self {
+        value_to_property_value(value).unwrap_or(PropertyValue::Null)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            PropertyValue::Text(text) => json!(text),
+            PropertyValue::Number(number) => {
+                if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+                    json!(*number as i64)
+                } else {
+                    json!(number)
+                }
+            }
+            PropertyValue::Bool(value) => json!(value),
+            PropertyValue::Date {
+                start,
+                end,
+                time_zone,
+            } => json!({ "start": start, "end": end, "time_zone": time_zone }),
+            PropertyValue::List(items) => json!(items),
+            PropertyValue::Object(value) => value.clone(),
+            PropertyValue::Null => serde_json::Value::Null,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            PropertyValue::Text(text) => Some(text.clone()),
+            PropertyValue::Number(number) => Some(number.to_string()),
+            PropertyValue::Bool(value) => Some(value.to_string()),
+            PropertyValue::Date { start, end, .. } => Some(match end {
+                Some(end) => format!("{}..{}", start, end),
+                None => start.clone(),
+            }),
+            PropertyValue::List(items) => Some(items.join(", ")),
+            PropertyValue::Object(value) => Some(value.to_string()),
+            PropertyValue::Null => None,
+        }
+    }
 }
 
 fn extract_page_properties(properties: &serde_json::Value) -> BTreeMap<String, PropertyValue> {
@@ -285,11 +830,13 @@ fn extract_page_properties(properties: &serde_json::Value) -> BTreeMap<String, P
         let value = match prop_type {
             "title" => extract_text_value(prop.get("title")),
             "rich_text" => extract_text_value(prop.get("rich_text")),
-            "select" => prop
-                .get("select")
-                .and_then(|v| v.get("name"))
-                .and_then(|v| v.as_str())
-                .map(|v| PropertyValue::Text(v.to_string())),
+            "select" => Some(
+                prop.get("select")
+                    .and_then(|v| v.get("name"))
+                    .and_then(|v| v.as_str())
+                    .map(|v| PropertyValue::Text(v.to_string()))
+                    .unwrap_or(PropertyValue::Null),
+            ),
             "multi_select" => prop
                 .get("multi_select")
                 .and_then(|v| v.as_array())
@@ -302,23 +849,28 @@ fn extract_page_properties(properties: &serde_json::Value) -> BTreeMap<String, P
                             .collect(),
                     )
                 }),
-            "status" => prop
-                .get("status")
-                .and_then(|v| v.get("name"))
-                .and_then(|v| v.as_str())
-                .map(|v| PropertyValue::Text(v.to_string())),
-            "number" => prop
-                .get("number")
-                .and_then(|v| v.as_f64())
-                .map(|v| PropertyValue::Text(v.to_string())),
+            "status" => Some(
+                prop.get("status")
+                    .and_then(|v| v.get("name"))
+                    .and_then(|v| v.as_str())
+                    .map(|v| PropertyValue::Text(v.to_string()))
+                    .unwrap_or(PropertyValue::Null),
+            ),
+            "number" => Some(
+                prop.get("number")
+                    .and_then(|v| v.as_f64())
+                    .map(PropertyValue::Number)
+                    .unwrap_or(PropertyValue::Null),
+            ),
             "checkbox" => prop
                 .get("checkbox")
                 .and_then(|v| v.as_bool())
-                .map(|v| PropertyValue::Text(v.to_string())),
-            "date" => prop
-                .get("date")
-                .and_then(extract_date_value)
-                .map(PropertyValue::Text),
+                .map(PropertyValue::Bool),
+            "date" => Some(
+                prop.get("date")
+                    .and_then(extract_date)
+                    .unwrap_or(PropertyValue::Null),
+            ),
             "people" => prop
                 .get("people")
                 .and_then(|v| v.as_array())
@@ -372,18 +924,24 @@ fn extract_page_properties(properties: &serde_json::Value) -> BTreeMap<String, P
                             .collect(),
                     )
                 }),
-            "url" => prop
-                .get("url")
-                .and_then(|v| v.as_str())
-                .map(|v| PropertyValue::Text(v.to_string())),
-            "email" => prop
-                .get("email")
-                .and_then(|v| v.as_str())
-                .map(|v| PropertyValue::Text(v.to_string())),
-            "phone_number" => prop
-                .get("phone_number")
-                .and_then(|v| v.as_str())
-                .map(|v| PropertyValue::Text(v.to_string())),
+            "url" => Some(
+                prop.get("url")
+                    .and_then(|v| v.as_str())
+                    .map(|v| PropertyValue::Text(v.to_string()))
+                    .unwrap_or(PropertyValue::Null),
+            ),
+            "email" => Some(
+                prop.get("email")
+                    .and_then(|v| v.as_str())
+                    .map(|v| PropertyValue::Text(v.to_string()))
+                    .unwrap_or(PropertyValue::Null),
+            ),
+            "phone_number" => Some(
+                prop.get("phone_number")
+                    .and_then(|v| v.as_str())
+                    .map(|v| PropertyValue::Text(v.to_string()))
+                    .unwrap_or(PropertyValue::Null),
+            ),
             "created_time" => prop
                 .get("created_time")
                 .and_then(|v| v.as_str())
@@ -408,8 +966,11 @@ fn extract_page_properties(properties: &serde_json::Value) -> BTreeMap<String, P
                 .get("unique_id")
                 .and_then(|v| {
                     let number = v.get("number")?.as_i64()?;
-                    let prefix = v.get("prefix").and_then(|p| p.as_str()).unwrap_or("");
-                    Some(PropertyValue::Text(format!("{}{}", prefix, number)))
+                    let value = match v.get("prefix").and_then(|p| p.as_str()) {
+                        Some(prefix) => PropertyValue::Text(format!("{}{}", prefix, number)),
+                        None => PropertyValue::Number(number as f64),
+                    };
+                    Some(value)
                 }),
             _ => prop.get(prop_type).and_then(value_to_property_value),
         };
@@ -434,6 +995,18 @@ fn extract_text_value(value: Option<&serde_json::Value>) -> Option<PropertyValue
     Some(PropertyValue::Text(out))
 }
 
+fn extract_date(value: &serde_json::Value) -> Option<PropertyValue> {
+    let start = value.get("start")?.as_str()?;
+    Some(PropertyValue::Date {
+        start: start.to_string(),
+        end: value.get("end").and_then(|v| v.as_str()).map(|v| v.to_string()),
+        time_zone: value
+            .get("time_zone")
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string()),
+    })
+}
+
 fn extract_date_value(value: &serde_json::Value) -> Option<String> {
     let start = value.get("start")?.as_str()?;
     let end = value.get("end").and_then(|v| v.as_str());
@@ -452,7 +1025,7 @@ fn extract_date_value(value: &serde_json::Value) -> Option<String> {
 
 fn extract_formula_value(value: &serde_json::Value) -> Option<PropertyValue> {
     let prop_type = value.get("type").and_then(|v| v.as_str())?;
-    match prop_type {
+    let value = match prop_type {
         "string" => value
             .get("string")
             .and_then(|v| v.as_str())
@@ -460,17 +1033,15 @@ fn extract_formula_value(value: &serde_json::Value) -> Option<PropertyValue> {
         "number" => value
             .get("number")
             .and_then(|v| v.as_f64())
-            .map(|v| PropertyValue::Text(v.to_string())),
+            .map(PropertyValue::Number),
         "boolean" => value
             .get("boolean")
             .and_then(|v| v.as_bool())
-            .map(|v| PropertyValue::Text(v.to_string())),
-        "date" => value
-            .get("date")
-            .and_then(extract_date_value)
-            .map(PropertyValue::Text),
-        _ => None,
-    }
+            .map(PropertyValue::Bool),
+        "date" => value.get("date").and_then(extract_date),
+        _ => return None,
+    };
+    Some(value.unwrap_or(PropertyValue::Null))
 }
 
 fn extract_rollup_value(value: &serde_json::Value) -> Option<PropertyValue> {
@@ -480,14 +1051,19 @@ fn extract_rollup_value(value: &serde_json::Value) -> Option<PropertyValue> {
             .get("array")
             .and_then(|v| v.as_array())
             .map(|items| PropertyValue::List(items.iter().filter_map(rollup_item_to_string).collect())),
-        "number" => value
-            .get("number")
-            .and_then(|v| v.as_f64())
-            .map(|v| PropertyValue::Text(v.to_string())),
-        "date" => value
-            .get("date")
-            .and_then(extract_date_value)
-            .map(PropertyValue::Text),
+        "number" => Some(
+            value
+                .get("number")
+                .and_then(|v| v.as_f64())
+                .map(PropertyValue::Number)
+                .unwrap_or(PropertyValue::Null),
+        ),
+        "date" => Some(
+            value
+                .get("date")
+                .and_then(extract_date)
+                .unwrap_or(PropertyValue::Null),
+        ),
         _ => None,
     }
 }
@@ -570,10 +1146,7 @@ fn rollup_item_to_string(value: &serde_json::Value) -> Option<String> {
         "formula" => value
             .get("formula")
             .and_then(extract_formula_value)
-            .map(|v| match v {
-                PropertyValue::Text(text) => text,
-                PropertyValue::List(list) => list.join(", "),
-            }),
+            .and_then(|v| v.as_text()),
         _ => value_to_string(value),
     }
 }
@@ -581,12 +1154,13 @@ fn rollup_item_to_string(value: &serde_json::Value) -> Option<String> {
 fn value_to_property_value(value: &serde_json::Value) -> Option<PropertyValue> {
     match value {
         serde_json::Value::String(value) => Some(PropertyValue::Text(value.clone())),
-        serde_json::Value::Number(value) => Some(PropertyValue::Text(value.to_string())),
-        serde_json::Value::Bool(value) => Some(PropertyValue::Text(value.to_string())),
+        serde_json::Value::Number(value) => value.as_f64().map(PropertyValue::Number),
+        serde_json::Value::Bool(value) => Some(PropertyValue::Bool(*value)),
         serde_json::Value::Array(values) => {
             Some(PropertyValue::List(values.iter().filter_map(value_to_string).collect()))
         }
-        _ => value_to_string(value).map(PropertyValue::Text),
+        serde_json::Value::Object(_) => Some(PropertyValue::Object(value.clone())),
+        serde_json::Value::Null => Some(PropertyValue::Null),
     }
 }
 
@@ -606,10 +1180,16 @@ pub struct Block {
     #[serde(rename = "type")]
     pub block_type: String,
     pub has_children: bool,
+    #[serde(skip)]
+    pub truncated: bool,
+    #[serde(skip)]
+    pub children: Vec<Block>,
+    #[serde(skip)]
+    pub raw: serde_json::Value,
     pub paragraph: Option<RichTextContainer>,
-    pub heading_1: Option<RichTextContainer>,
-    pub heading_2: Option<RichTextContainer>,
-    pub heading_3: Option<RichTextContainer>,
+    pub heading_1: Option<HeadingContainer>,
+    pub heading_2: Option<HeadingContainer>,
+    pub heading_3: Option<HeadingContainer>,
     pub bulleted_list_item: Option<RichTextContainer>,
     pub numbered_list_item: Option<RichTextContainer>,
     pub to_do: Option<ToDoContainer>,
@@ -631,40 +1211,36 @@ pub struct Block {
     pub audio: Option<FileContainer>,
     pub embed: Option<EmbedContainer>,
     pub link_to_page: Option<LinkToPageContainer>,
+    pub column_list: Option<EmptyContainer>,
+    pub column: Option<EmptyContainer>,
+    pub synced_block: Option<SyncedBlockContainer>,
+    pub template: Option<RichTextContainer>,
+    pub breadcrumb: Option<EmptyContainer>,
+    pub table_of_contents: Option<EmptyContainer>,
+    pub link_preview: Option<EmbedContainer>,
 }
 
 impl Block {
-    pub fn children_marker(id: &str) -> Self {
-        Self {
-            id: format!("{}::children", id),
-            block_type: "children".to_string(),
-            has_children: false,
-            paragraph: None,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            bulleted_list_item: None,
-            numbered_list_item: None,
-            to_do: None,
-            quote: None,
-            code: None,
-            callout: None,
-            divider: None,
-            image: None,
-            bookmark: None,
-            toggle: None,
-            equation: None,
-            child_page: None,
-            child_database: None,
-            table: None,
-            table_row: None,
-            file: None,
-            pdf: None,
-            video: None,
-            audio: None,
-            embed: None,
-            link_to_page: None,
-        }
+    /// Keeps the untouched API payload next to the typed fields so block
+    /// types this crate doesn't model survive in lossless exports.
+    fn from_raw(raw: serde_json::Value) -> Result<Self> {
+        let mut block: Block = serde_json::from_value(raw.clone())?;
+        block.raw = raw;
+        Ok(block)
+    }
+
+    /// Whether this block is a sub-page or inline database rather than
+    /// content of the page it appears on.
+    pub fn is_child_document(&self) -> bool {
+        matches!(self.block_type.as_str(), "child_page" | "child_database")
+    }
+
+    fn children_source_id(&self) -> &str {
+        self.synced_block
+            .as_ref()
+            .and_then(|synced| synced.synced_from.as_ref())
+            .map(|from| from.block_id.as_str())
+            .unwrap_or(&self.id)
     }
 }
 
@@ -673,6 +1249,13 @@ pub struct RichTextContainer {
     pub rich_text: Vec<RichText>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HeadingContainer {
+    pub rich_text: Vec<RichText>,
+    #[serde(default)]
+    pub is_toggleable: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ToDoContainer {
     pub rich_text: Vec<RichText>,
@@ -726,6 +1309,8 @@ pub struct ImageContainer {
     pub r#type: Option<String>,
     pub file: Option<FileObject>,
     pub external: Option<ExternalObject>,
+    #[serde(default)]
+    pub caption: Vec<RichText>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -735,6 +1320,8 @@ pub struct FileContainer {
     pub file: Option<FileObject>,
     pub external: Option<ExternalObject>,
     pub name: Option<String>,
+    #[serde(default)]
+    pub caption: Vec<RichText>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -750,6 +1337,16 @@ pub struct LinkToPageContainer {
     pub database_id: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SyncedBlockContainer {
+    pub synced_from: Option<SyncedFrom>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncedFrom {
+    pub block_id: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FileObject {
     pub url: String,
@@ -762,10 +1359,42 @@ pub struct ExternalObject {
 
 #[derive(Debug, Deserialize)]
 pub struct RichText {
+    #[serde(rename = "type", default)]
+    pub text_type: Option<String>,
     pub plain_text: String,
     #[serde(default)]
     pub annotations: Option<Annotations>,
     pub href: Option<String>,
+    pub mention: Option<Mention>,
+    pub equation: Option<EquationContainer>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Mention {
+    #[serde(rename = "type")]
+    pub mention_type: String,
+    pub page: Option<MentionTarget>,
+    pub database: Option<MentionTarget>,
+    pub user: Option<MentionUser>,
+    pub date: Option<MentionDate>,
+    pub link_preview: Option<EmbedContainer>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MentionTarget {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MentionUser {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MentionDate {
+    pub start: String,
+    pub end: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -780,4 +1409,22 @@ pub struct Annotations {
     pub underline: bool,
     #[serde(default)]
     pub code: bool,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_is_clamped_before_conversion() {
+        assert_eq!(retry_after_delay("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(retry_after_delay(" 120 "), Some(MAX_BACKOFF));
+        assert_eq!(retry_after_delay("1e30"), Some(MAX_BACKOFF));
+        assert_eq!(retry_after_delay("-1"), None);
+        assert_eq!(retry_after_delay("inf"), None);
+        assert_eq!(retry_after_delay("NaN"), None);
+        assert_eq!(retry_after_delay("soon"), None);
+    }
 }
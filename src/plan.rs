@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use opendal::ErrorKind;
+use serde::Serialize;
+
+use crate::storage::StorageBackend;
+use crate::{AppState, DatabaseState};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Page,
+    Blob,
+    Index,
+    Table,
+    Schema,
+}
+
+impl EntryKind {
+    const ALL: [EntryKind; 5] = [
+        EntryKind::Page,
+        EntryKind::Blob,
+        EntryKind::Index,
+        EntryKind::Table,
+        EntryKind::Schema,
+    ];
+
+    fn plural(self) -> &'static str {
+        match self {
+            EntryKind::Page => "pages",
+            EntryKind::Blob => "blobs",
+            EntryKind::Index => "indexes",
+            EntryKind::Table => "tables",
+            EntryKind::Schema => "schemas",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Created,
+    Updated,
+    Unchanged,
+    Deleted,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlannedChange {
+    pub kind: EntryKind,
+    pub change: Change,
+    pub database: String,
+    pub storage: String,
+    pub path: String,
+}
+
+/// Collects what a dry run would have written or deleted instead of touching
+/// storage.
+#[derive(Default)]
+pub struct DryRun {
+    changes: Mutex<Vec<PlannedChange>>,
+}
+
+impl DryRun {
+    pub fn record(
+        &self,
+        kind: EntryKind,
+        change: Change,
+        database: &DatabaseState,
+        storage: &StorageBackend,
+        path: &str,
+    ) {
+        self.changes
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(PlannedChange {
+                kind,
+                change,
+                database: database.id.clone(),
+                storage: storage.name.clone(),
+                path: path.to_string(),
+            });
+    }
+
+    /// Compares `content` with what `storage` holds at `path` and records
+    /// whether writing it would create, update or leave the file unchanged.
+    pub async fn diff(
+        &self,
+        kind: EntryKind,
+        database: &DatabaseState,
+        storage: &StorageBackend,
+        path: &str,
+        content: &[u8],
+    ) -> Result<()> {
+        let change = match storage.op.stat(path).await {
+            Ok(meta) if meta.content_length() != content.len() as u64 => Change::Updated,
+            Ok(_) => {
+                let existing = storage
+                    .op
+                    .read(path)
+                    .await
+                    .with_context(|| format!("failed to read {path}"))?;
+                if existing.to_vec() == content {
+                    Change::Unchanged
+                } else {
+                    Change::Updated
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Change::Created,
+            Err(err) => return Err(err).with_context(|| format!("failed to stat {path}")),
+        };
+        self.record(kind, change, database, storage, path);
+        Ok(())
+    }
+
+    fn changes(&self) -> Vec<PlannedChange> {
+        let mut changes = self
+            .changes
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+        changes.sort_by(|a, b| {
+            (a.kind, a.change, &a.storage, &a.path).cmp(&(b.kind, b.change, &b.storage, &b.path))
+        });
+        changes.dedup_by(|a, b| {
+            a.kind == b.kind && a.change == b.change && a.storage == b.storage && a.path == b.path
+        });
+        changes
+    }
+
+    fn summary(changes: &[PlannedChange]) -> BTreeMap<EntryKind, BTreeMap<Change, usize>> {
+        let mut summary: BTreeMap<EntryKind, BTreeMap<Change, usize>> = BTreeMap::new();
+        for kind in EntryKind::ALL {
+            let counts = summary.entry(kind).or_default();
+            for change in [Change::Created, Change::Updated, Change::Unchanged, Change::Deleted] {
+                counts.insert(change, 0);
+            }
+        }
+        for change in changes {
+            *summary
+                .entry(change.kind)
+                .or_default()
+                .entry(change.change)
+                .or_default() += 1;
+        }
+        summary
+    }
+
+    /// Counts per kind followed by every change other than `unchanged`.
+    pub fn report(&self) -> String {
+        let changes = self.changes();
+        let mut out = String::new();
+        for (kind, counts) in Self::summary(&changes) {
+            let counts = counts
+                .iter()
+                .map(|(change, count)| format!("{} {}", count, label(change)))
+                .collect::<Vec<_>>();
+            out.push_str(&format!("{}: {}\n", kind.plural(), counts.join(", ")));
+        }
+        for change in changes.iter().filter(|change| change.change != Change::Unchanged) {
+            out.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                label(&change.change),
+                label(&change.kind),
+                change.storage,
+                change.path
+            ));
+        }
+        out
+    }
+
+    pub fn report_json(&self) -> Result<String> {
+        let changes = self.changes();
+        let report = serde_json::json!({
+            "summary": Self::summary(&changes),
+            "changes": changes,
+        });
+        Ok(serde_json::to_string_pretty(&report)?)
+    }
+}
+
+/// Writes a generated file to `storage`, or in a dry run records what writing
+/// it would change.
+pub async fn write_file(
+    state: &AppState,
+    database: &DatabaseState,
+    storage: &StorageBackend,
+    kind: EntryKind,
+    path: &str,
+    body: Vec<u8>,
+) -> Result<()> {
+    match state.dry_run.as_ref() {
+        Some(plan) => plan.diff(kind, database, storage, path, &body).await,
+        None => {
+            storage.op.write(path, body).await?;
+            Ok(())
+        }
+    }
+}
+
+fn label<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
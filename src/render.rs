@@ -1,10 +1,32 @@
-use crate::notion::{Block, FileContainer, PageMetadata, PropertyValue, RichText, RichTextContainer};
-use serde_yaml::{Mapping, Value as YamlValue};
+use crate::config::{FormatConfig, OutputFormat, RenderConfig};
+use crate::notion::{
+    normalize_id, page_id_from_url, Block, FileContainer, HeadingContainer, PageMetadata,
+    PropertyValue, RichText,
+};
 use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+mod html;
+mod json;
+mod markdown;
+
+pub use html::HtmlRenderer;
+pub use json::JsonRenderer;
+pub use markdown::MarkdownRenderer;
 
 pub struct Rendered {
-    pub markdown: String,
+    pub content: String,
     pub blobs: Vec<BlobRef>,
+    pub links: BTreeMap<String, Option<String>>,
+}
+
+pub struct LinkTarget {
+    pub path: String,
+    pub title: Option<String>,
+}
+
+pub trait LinkResolver: Sync {
+    fn resolve(&self, page_id: &str) -> Option<LinkTarget>;
 }
 
 #[derive(Clone, Debug)]
@@ -13,352 +35,290 @@ pub struct BlobRef {
     pub url: String,
 }
 
-pub fn render_page(
-    metadata: &PageMetadata,
-    blocks: &[Block],
-    key_map: &BTreeMap<String, String>,
-    property_includes: Option<&HashSet<String>>,
-) -> Rendered {
-    let mut out = String::new();
-    let mut numbering = 1usize;
-    let mut table_state: Option<TableState> = None;
-    let mut blobs: Vec<BlobRef> = Vec::new();
-
-    let mut front_matter = Mapping::new();
-    let mut notion_meta = Mapping::new();
-    notion_meta.insert(
-        YamlValue::String("page_id".to_string()),
-        YamlValue::String(metadata.id.clone()),
-    );
-    if let Some(database_id) = metadata.parent.database_id.as_ref() {
-        notion_meta.insert(
-            YamlValue::String("database_id".to_string()),
-            YamlValue::String(database_id.clone()),
-        );
+/// Everything a renderer needs to know about a page. `path` is the base path
+/// from the path template; each output maps it to its own location.
+pub struct PageInput<'a> {
+    pub metadata: &'a PageMetadata,
+    pub blocks: &'a [Block],
+    pub key_map: &'a BTreeMap<String, String>,
+    pub property_includes: Option<&'a HashSet<String>>,
+    pub options: &'a RenderConfig,
+    pub path: &'a str,
+    pub resolver: &'a dyn LinkResolver,
+    /// Generated row indexes of inline databases, keyed by block id.
+    pub child_indexes: &'a BTreeMap<String, String>,
+}
+
+impl PageInput<'_> {
+    /// Properties that pass the include filter, keyed by their mapped name.
+    pub fn properties(&self) -> impl Iterator<Item = (&str, &PropertyValue)> {
+        self.metadata
+            .properties
+            .iter()
+            .filter(|(key, _)| {
+                self.property_includes
+                    .is_none_or(|includes| includes.contains(*key))
+            })
+            .filter_map(|(key, value)| {
+                let mapped = self.key_map.get(key).map(String::as_str).unwrap_or(key);
+                (!mapped.is_empty()).then_some((mapped, value))
+            })
     }
-    front_matter.insert(YamlValue::String("_notion".to_string()), YamlValue::Mapping(notion_meta));
-    for (key, value) in &metadata.properties {
-        if let Some(includes) = property_includes && !includes.contains(key) {
-            continue;
+}
+
+pub trait Renderer: Send + Sync {
+    fn extension(&self) -> &'static str;
+    fn render(&self, page: &PageInput, ctx: &mut RenderContext) -> String;
+}
+
+#[derive(Clone)]
+pub struct OutputTarget {
+    pub format: OutputFormat,
+    prefix: String,
+    renderer: Arc<dyn Renderer>,
+}
+
+impl OutputTarget {
+    pub fn new(config: &FormatConfig) -> Self {
+        let renderer: Arc<dyn Renderer> = match config.r#type {
+            OutputFormat::Markdown => Arc::new(MarkdownRenderer),
+            OutputFormat::Html => Arc::new(HtmlRenderer),
+            OutputFormat::Json => Arc::new(JsonRenderer),
+        };
+        let prefix = config.prefix.clone().unwrap_or_else(|| match config.r#type {
+            OutputFormat::Markdown | OutputFormat::Json => String::new(),
+            OutputFormat::Html => "html".to_string(),
+        });
+        Self {
+            format: config.r#type,
+            prefix: prefix.trim_matches('/').to_string(),
+            renderer,
         }
-        let mapped_key = key_map.get(key).map(|v| v.as_str()).unwrap_or(key);
-        if mapped_key.is_empty() {
-            continue;
+    }
+
+    /// Maps a base page path to this output. Markdown keeps the template's
+    /// file name as written; other formats swap in their own extension.
+    pub fn page_path(&self, base: &str) -> String {
+        let path = if self.format == OutputFormat::Markdown {
+            base.to_string()
+        } else {
+            let (dir, file) = match base.rsplit_once('/') {
+                Some((dir, file)) => (Some(dir), file),
+                None => (None, base),
+            };
+            let stem = match file.rsplit_once('.') {
+                Some((stem, _)) if !stem.is_empty() => stem,
+                _ => file,
+            };
+            let file = format!("{}.{}", stem, self.renderer.extension());
+            match dir {
+                Some(dir) => format!("{}/{}", dir, file),
+                None => file,
+            }
+        };
+        if self.prefix.is_empty() {
+            path
+        } else {
+            format!("{}/{}", self.prefix, path)
         }
-        let yaml_value = match value {
-            PropertyValue::Text(value) => YamlValue::String(value.clone()),
-            PropertyValue::List(values) => YamlValue::Sequence(
-                values
-                    .iter()
-                    .map(|item| YamlValue::String(item.clone()))
-                    .collect(),
-            ),
+    }
+
+    pub fn render(&self, page: &PageInput) -> Rendered {
+        let page_path = self.page_path(page.path);
+        let mut ctx = RenderContext {
+            options: page.options,
+            page_path: &page_path,
+            output: self,
+            resolver: page.resolver,
+            child_indexes: page.child_indexes,
+            blobs: Vec::new(),
+            links: BTreeMap::new(),
+            headings: collect_headings(page.blocks),
         };
-        front_matter.insert(YamlValue::String(mapped_key.to_string()), yaml_value);
+        let content = self.renderer.render(page, &mut ctx);
+        Rendered {
+            content,
+            blobs: ctx.blobs,
+            links: ctx.links,
+        }
     }
-    let yaml = serde_yaml::to_string(&front_matter).unwrap_or_default();
-    let yaml = yaml.strip_prefix("---\n").unwrap_or(&yaml);
-    out.push_str("---\n");
-    out.push_str(yaml);
-    if !yaml.ends_with('\n') {
-        out.push('\n');
+}
+
+impl Default for OutputTarget {
+    fn default() -> Self {
+        Self::new(&FormatConfig {
+            r#type: OutputFormat::Markdown,
+            prefix: None,
+        })
     }
-    out.push_str("---\n\n");
-
-    for block in blocks {
-        if table_state.is_some()
-            && block.block_type != "table_row"
-            && block.block_type != "children"
-        {
-            flush_table(&mut out, table_state.take());
+}
+
+pub struct RenderContext<'a> {
+    options: &'a RenderConfig,
+    page_path: &'a str,
+    output: &'a OutputTarget,
+    resolver: &'a dyn LinkResolver,
+    child_indexes: &'a BTreeMap<String, String>,
+    blobs: Vec<BlobRef>,
+    links: BTreeMap<String, Option<String>>,
+    headings: Vec<Heading>,
+}
+
+impl RenderContext<'_> {
+    fn page_link(&mut self, page_id: &str) -> (String, Option<String>) {
+        let Some(id) = normalize_id(page_id) else {
+            return (notion_url(page_id), None);
+        };
+        let target = self.resolver.resolve(&id);
+        self.links
+            .insert(id.clone(), target.as_ref().map(|target| target.path.clone()));
+        match target {
+            Some(target) => (
+                relative_path(self.page_path, &self.output.page_path(&target.path)),
+                target.title,
+            ),
+            None => (notion_url(&id), None),
         }
+    }
 
-        match block.block_type.as_str() {
-            "paragraph" => {
-                if let Some(text) = block.paragraph.as_ref().map(render_rich_text) {
-                    out.push_str(&text);
-                    out.push_str("\n\n");
-                }
-            }
-            "heading_1" => {
-                if let Some(text) = block.heading_1.as_ref().map(render_rich_text) {
-                    out.push_str("# ");
-                    out.push_str(&text);
-                    out.push_str("\n\n");
-                }
-            }
-            "heading_2" => {
-                if let Some(text) = block.heading_2.as_ref().map(render_rich_text) {
-                    out.push_str("## ");
-                    out.push_str(&text);
-                    out.push_str("\n\n");
-                }
-            }
-            "heading_3" => {
-                if let Some(text) = block.heading_3.as_ref().map(render_rich_text) {
-                    out.push_str("### ");
-                    out.push_str(&text);
-                    out.push_str("\n\n");
-                }
-            }
-            "bulleted_list_item" => {
-                if let Some(text) = block.bulleted_list_item.as_ref().map(render_rich_text) {
-                    out.push_str("- ");
-                    out.push_str(&text);
-                    out.push('\n');
-                }
-            }
-            "numbered_list_item" => {
-                if let Some(text) = block.numbered_list_item.as_ref().map(render_rich_text) {
-                    out.push_str(&format!("{}. {}\n", numbering, text));
-                    numbering += 1;
-                }
-            }
-            "to_do" => {
-                if let Some(todo) = block.to_do.as_ref() {
-                    let mark = if todo.checked { "x" } else { " " };
-                    out.push_str(&format!(
-                        "- [{}] {}\n",
-                        mark,
-                        render_rich_text_vec(&todo.rich_text)
-                    ));
-                }
-            }
-            "quote" => {
-                if let Some(text) = block.quote.as_ref().map(render_rich_text) {
-                    out.push_str("> ");
-                    out.push_str(&text);
-                    out.push_str("\n\n");
-                }
-            }
-            "code" => {
-                if let Some(code) = block.code.as_ref() {
-                    let lang = code.language.as_deref().unwrap_or("");
-                    out.push_str(&format!("```{}\n", lang));
-                    out.push_str(&render_rich_text_vec(&code.rich_text));
-                    out.push_str("\n```\n\n");
-                }
-            }
-            "callout" => {
-                if let Some(callout) = block.callout.as_ref() {
-                    let text = render_rich_text_vec(&callout.rich_text);
-                    out.push_str("> [!NOTE]\n> ");
-                    out.push_str(&text);
-                    out.push_str("\n\n");
-                }
-            }
-            "divider" => {
-                out.push_str("---\n\n");
-            }
-            "image" => {
-                if let Some(image) = block.image.as_ref()
-                    && let Some(url) = image
-                        .file
-                        .as_ref()
-                        .map(|file| file.url.clone())
-                        .or_else(|| image.external.as_ref().map(|ext| ext.url.clone()))
-                {
-                    let blob_path = build_blob_path(&block.id, None, Some(&url));
-                    blobs.push(BlobRef {
-                        path: blob_path.clone(),
-                        url,
-                    });
-                    out.push_str(&format!("![]({})\n\n", format_blob_link(&blob_path)));
-                }
-            }
-            "bookmark" => {
-                if let Some(bookmark) = block.bookmark.as_ref() {
-                    out.push_str(&format!("[{}]({})\n\n", bookmark.url, bookmark.url));
-                }
-            }
-            "toggle" => {
-                if let Some(text) = block.toggle.as_ref().map(render_rich_text) {
-                    out.push_str(&format!("> **Toggle:** {}\n\n", text));
-                }
-            }
-            "equation" => {
-                if let Some(eq) = block.equation.as_ref() {
-                    out.push_str(&format!("$$\n{}\n$$\n\n", eq.expression));
-                }
-            }
-            "child_page" => {
-                if let Some(child) = block.child_page.as_ref() {
-                    out.push_str(&format!("- [Page] {}\n\n", child.title));
-                }
-            }
-            "child_database" => {
-                if let Some(child) = block.child_database.as_ref() {
-                    out.push_str(&format!("- [Database] {}\n\n", child.title));
-                }
-            }
-            "table" => {
-                if let Some(table) = block.table.as_ref() {
-                    table_state = Some(TableState::new(
-                        table.table_width,
-                        table.has_column_header,
-                        table.has_row_header,
-                    ));
-                }
-            }
-            "table_row" => {
-                if let (Some(row), Some(state)) =
-                    (block.table_row.as_ref(), table_state.as_mut())
-                {
-                    let cells = row
-                        .cells
-                        .iter()
-                        .map(|cell| render_rich_text_vec(cell))
-                        .collect::<Vec<_>>();
-                    state.rows.push(cells);
-                }
-            }
-            "file" => {
-                if let Some(link) = render_file_link(block.file.as_ref(), &block.id) {
-                    blobs.push(BlobRef {
-                        path: link.path.clone(),
-                        url: link.url,
-                    });
-                    out.push_str(&format!(
-                        "[{}]({})\n\n",
-                        link.label,
-                        format_blob_link(&link.path)
-                    ));
-                }
-            }
-            "pdf" => {
-                if let Some(link) = render_file_link(block.pdf.as_ref(), &block.id) {
-                    blobs.push(BlobRef {
-                        path: link.path.clone(),
-                        url: link.url,
-                    });
-                    out.push_str(&format!(
-                        "[{}]({})\n\n",
-                        link.label,
-                        format_blob_link(&link.path)
-                    ));
+    fn child_index_link(&self, database_id: &str) -> Option<String> {
+        let index = self.child_indexes.get(database_id)?;
+        Some(relative_path(self.page_path, &self.output.page_path(index)))
+    }
+
+    fn blob_link(&mut self, path: String, url: String) -> String {
+        let href = relative_path(self.page_path, &path);
+        self.blobs.push(BlobRef { path, url });
+        href
+    }
+}
+
+/// Resolves the display text and link target of a rich text item, following
+/// mentions and rewriting Notion page URLs to synced pages.
+fn rich_text_target(item: &RichText, ctx: &mut RenderContext) -> (String, Option<String>) {
+    let mut href = item.href.clone();
+    let mut text = item.plain_text.clone();
+    if item.text_type.as_deref() == Some("mention")
+        && let Some(mention) = item.mention.as_ref()
+    {
+        match mention.mention_type.as_str() {
+            "page" => {
+                if let Some(target) = mention.page.as_ref() {
+                    href = Some(ctx.page_link(&target.id).0);
                 }
             }
-            "video" => {
-                if let Some(link) = render_file_link(block.video.as_ref(), &block.id) {
-                    blobs.push(BlobRef {
-                        path: link.path.clone(),
-                        url: link.url,
-                    });
-                    out.push_str(&format!(
-                        "[{}]({})\n\n",
-                        link.label,
-                        format_blob_link(&link.path)
-                    ));
+            "database" => {
+                if let Some(target) = mention.database.as_ref() {
+                    href = href.or_else(|| Some(notion_url(&target.id)));
                 }
             }
-            "audio" => {
-                if let Some(link) = render_file_link(block.audio.as_ref(), &block.id) {
-                    blobs.push(BlobRef {
-                        path: link.path.clone(),
-                        url: link.url,
-                    });
-                    out.push_str(&format!(
-                        "[{}]({})\n\n",
-                        link.label,
-                        format_blob_link(&link.path)
-                    ));
+            "user" => {
+                if let Some(name) = mention.user.as_ref().and_then(|user| user.name.as_ref()) {
+                    text = format!("@{}", name);
                 }
             }
-            "embed" => {
-                if let Some(embed) = block.embed.as_ref() {
-                    out.push_str(&format!("[Embed]({})\n\n", embed.url));
+            "date" => {
+                if let Some(date) = mention.date.as_ref() {
+                    text = match date.end.as_ref() {
+                        Some(end) => format!("{} → {}", date.start, end),
+                        None => date.start.clone(),
+                    };
                 }
             }
-            "link_to_page" => {
-                if let Some(link) = block.link_to_page.as_ref() {
-                    let target = link
-                        .page_id
-                        .as_deref()
-                        .or(link.database_id.as_deref())
-                        .unwrap_or("unknown");
-                    out.push_str(&format!("[Link] {}\n\n", target));
+            "link_preview" => {
+                if let Some(preview) = mention.link_preview.as_ref() {
+                    href = Some(preview.url.clone());
                 }
             }
-            "children" => {
-                out.push('\n');
-                numbering = 1;
-            }
             _ => {}
         }
-
-        if block.block_type != "numbered_list_item" {
-            numbering = 1;
-        }
-    }
-
-    if table_state.is_some() {
-        flush_table(&mut out, table_state.take());
-    }
-
-    Rendered {
-        markdown: out,
-        blobs,
+        return (text, href);
     }
-}
 
-fn render_rich_text(container: &RichTextContainer) -> String {
-    render_rich_text_vec(&container.rich_text)
+    let href = href.map(|link| match page_id_from_url(&link) {
+        Some(page_id) => ctx.page_link(&page_id).0,
+        None => link,
+    });
+    (text, href)
 }
 
-fn render_rich_text_vec(rich_text: &[RichText]) -> String {
-    rich_text
-        .iter()
-        .map(render_rich_text_item)
-        .collect::<Vec<_>>()
-        .join("")
+struct Heading {
+    block_id: String,
+    level: usize,
+    text: String,
+    anchor: String,
 }
 
-fn render_rich_text_item(item: &RichText) -> String {
-    let mut text = item.plain_text.clone();
-    if let Some(annotations) = item.annotations.as_ref() {
-        if annotations.code {
-            text = format!("`{}`", text);
-        } else {
-            if annotations.bold {
-                text = format!("**{}**", text);
-            }
-            if annotations.italic {
-                text = format!("*{}*", text);
-            }
-            if annotations.strikethrough {
-                text = format!("~~{}~~", text);
-            }
-            if annotations.underline {
-                text = format!("<u>{}</u>", text);
-            }
+fn collect_headings(blocks: &[Block]) -> Vec<Heading> {
+    fn walk<'a>(blocks: &'a [Block], out: &mut Vec<(&'a str, usize, &'a HeadingContainer)>) {
+        for block in blocks {
+            let heading = match block.block_type.as_str() {
+                "heading_1" => block.heading_1.as_ref().map(|h| (block.id.as_str(), 1, h)),
+                "heading_2" => block.heading_2.as_ref().map(|h| (block.id.as_str(), 2, h)),
+                "heading_3" => block.heading_3.as_ref().map(|h| (block.id.as_str(), 3, h)),
+                _ => None,
+            };
+            out.extend(heading);
+            walk(&block.children, out);
         }
     }
 
-    if let Some(href) = item.href.as_ref() {
-        text = format!("[{}]({})", text, href);
-    }
+    let mut found = Vec::new();
+    walk(blocks, &mut found);
+    let mut used: BTreeMap<String, usize> = BTreeMap::new();
+    found
+        .into_iter()
+        .map(|(block_id, level, heading)| {
+            let plain = plain_text(&heading.rich_text);
+            let base = heading_anchor(&plain);
+            let count = used.entry(base.clone()).or_insert(0);
+            let anchor = if *count == 0 {
+                base
+            } else {
+                format!("{}-{}", base, count)
+            };
+            *count += 1;
+            Heading {
+                block_id: block_id.to_string(),
+                level,
+                text: plain,
+                anchor,
+            }
+        })
+        .collect()
+}
 
-    text
+fn heading_anchor(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
 }
 
-struct TableState {
-    rows: Vec<Vec<String>>,
-    width: usize,
-    has_column_header: bool,
-    has_row_header: bool,
+fn plain_text(rich_text: &[RichText]) -> String {
+    rich_text.iter().map(|item| item.plain_text.as_str()).collect()
 }
 
-impl TableState {
-    fn new(width: usize, has_column_header: bool, has_row_header: bool) -> Self {
-        Self {
-            rows: Vec::new(),
-            width,
-            has_column_header,
-            has_row_header,
-        }
+fn color_style(color: &str) -> Option<String> {
+    if color == "default" {
+        return None;
+    }
+    match color.strip_suffix("_background") {
+        Some(background) => Some(format!("background-color: {}", background)),
+        None => Some(format!("color: {}", color)),
     }
 }
 
+fn notion_url(id: &str) -> String {
+    format!("https://www.notion.so/{}", id.replace('-', ""))
+}
+
 struct FileLink {
     label: String,
     url: String,
@@ -405,56 +365,17 @@ fn extract_extension_from_url(url: &str) -> Option<String> {
     extract_extension_from_name(filename)
 }
 
-fn format_blob_link(path: &str) -> String {
-    format!("../{}", path)
-}
-
-fn flush_table(out: &mut String, state: Option<TableState>) {
-    let Some(state) = state else { return };
-    if state.rows.is_empty() {
-        return;
-    }
-
-    let width = state
-        .rows
+pub fn relative_path(from_file: &str, to_file: &str) -> String {
+    let from_dirs = from_file.split('/').collect::<Vec<_>>();
+    let from_dirs = &from_dirs[..from_dirs.len().saturating_sub(1)];
+    let to_parts = to_file.split('/').collect::<Vec<_>>();
+    let common = from_dirs
         .iter()
-        .map(|row| row.len())
-        .max()
-        .unwrap_or(state.width)
-        .max(state.width);
-
-    let mut rows = state.rows;
-    for row in rows.iter_mut() {
-        while row.len() < width {
-            row.push(String::new());
-        }
-    }
-
-    let (header, body_start) = if state.has_column_header {
-        (rows[0].clone(), 1)
-    } else {
-        (vec![String::new(); width], 0)
-    };
-
-    let header = header
-        .into_iter()
-        .map(|cell| if cell.is_empty() { " ".to_string() } else { cell })
-        .collect::<Vec<_>>();
-
-    out.push('|');
-    out.push_str(&header.join(" | "));
-    out.push_str(" |\n|");
-    out.push_str(&vec!["---"; width].join(" | "));
-    out.push_str(" |\n");
-
-    for row in rows.into_iter().skip(body_start) {
-        let mut row_cells = row;
-        if state.has_row_header && !row_cells.is_empty() {
-            row_cells[0] = format!("**{}**", row_cells[0]);
-        }
-        out.push('|');
-        out.push_str(&row_cells.join(" | "));
-        out.push_str(" |\n");
-    }
-    out.push('\n');
+        .zip(to_parts.iter())
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to_parts.len().saturating_sub(1));
+    let mut parts = vec![".."; from_dirs.len() - common];
+    parts.extend(&to_parts[common..]);
+    parts.join("/")
 }
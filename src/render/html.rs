@@ -0,0 +1,517 @@
+use super::{
+    build_blob_path, color_style, notion_url, plain_text, render_file_link, rich_text_target,
+    PageInput, RenderContext, Renderer,
+};
+use crate::config::{ColumnStyle, ToggleStyle};
+use crate::notion::{Block, FileContainer, HeadingContainer, PropertyValue, RichText};
+
+const TRUNCATED_MARKER: &str =
+    "<p class=\"truncated\"><em>… nested content omitted: max_depth reached</em></p>\n";
+
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn render(&self, page: &PageInput, ctx: &mut RenderContext) -> String {
+        let title = page.metadata.title.as_deref().unwrap_or(&page.metadata.id);
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        out.push_str(&format!("<title>{}</title>\n", escape(title)));
+        out.push_str(&format!(
+            "<meta name=\"notion-page-id\" content=\"{}\">\n",
+            escape(&page.metadata.id)
+        ));
+        out.push_str("</head>\n<body>\n<article>\n");
+        out.push_str(&format!("<h1 class=\"page-title\">{}</h1>\n", escape(title)));
+
+        let properties = page.properties().collect::<Vec<_>>();
+        if !properties.is_empty() {
+            out.push_str("<dl class=\"properties\">\n");
+            for (key, value) in properties {
+                out.push_str(&format!(
+                    "<dt>{}</dt><dd>{}</dd>\n",
+                    escape(key),
+                    escape(&property_text(value))
+                ));
+            }
+            out.push_str("</dl>\n");
+        }
+
+        out.push_str(&render_blocks(page.blocks, ctx));
+        out.push_str("</article>\n</body>\n</html>\n");
+        out
+    }
+}
+
+fn property_text(value: &PropertyValue) -> String {
+    match value {
+        PropertyValue::Date { start, end, .. } => match end {
+            Some(end) => format!("{} → {}", start, end),
+            None => start.clone(),
+        },
+        PropertyValue::List(values) => values.join(", "),
+        PropertyValue::Object(value) => value.to_string(),
+        other => other.as_text().unwrap_or_default(),
+    }
+}
+
+fn render_blocks(blocks: &[Block], ctx: &mut RenderContext) -> String {
+    let mut out = String::new();
+    let mut open_list: Option<&str> = None;
+
+    for block in blocks {
+        let list = list_tag(block);
+        if open_list != list {
+            if let Some(tag) = open_list {
+                out.push_str(&format!("</{}>\n", tag));
+            }
+            if list.is_some() {
+                out.push_str(&list_open_tag(block));
+            }
+            open_list = list;
+        }
+        render_block(block, ctx, &mut out);
+    }
+
+    if let Some(tag) = open_list {
+        out.push_str(&format!("</{}>\n", tag));
+    }
+    out
+}
+
+fn list_tag(block: &Block) -> Option<&'static str> {
+    match block.block_type.as_str() {
+        "bulleted_list_item" | "to_do" => Some("ul"),
+        "numbered_list_item" => Some("ol"),
+        _ => None,
+    }
+}
+
+fn list_open_tag(block: &Block) -> String {
+    if block.block_type == "to_do" {
+        "<ul class=\"todo-list\">\n".to_string()
+    } else {
+        format!("<{}>\n", list_tag(block).unwrap_or("ul"))
+    }
+}
+
+fn render_children(block: &Block, ctx: &mut RenderContext) -> String {
+    let mut out = render_blocks(&block.children, ctx);
+    if block.truncated {
+        out.push_str(TRUNCATED_MARKER);
+    }
+    out
+}
+
+fn render_block(block: &Block, ctx: &mut RenderContext, out: &mut String) {
+    match block.block_type.as_str() {
+        "paragraph" => {
+            if let Some(container) = block.paragraph.as_ref() {
+                let text = render_rich_text(&container.rich_text, ctx);
+                out.push_str(&format!("<p>{}</p>\n", text));
+            }
+            push_indented(out, &render_children(block, ctx));
+        }
+        "heading_1" => push_heading(out, 1, block.heading_1.as_ref(), block, ctx),
+        "heading_2" => push_heading(out, 2, block.heading_2.as_ref(), block, ctx),
+        "heading_3" => push_heading(out, 3, block.heading_3.as_ref(), block, ctx),
+        "bulleted_list_item" | "numbered_list_item" => {
+            let container = block
+                .bulleted_list_item
+                .as_ref()
+                .or(block.numbered_list_item.as_ref());
+            if let Some(container) = container {
+                let text = render_rich_text(&container.rich_text, ctx);
+                push_list_item(out, &text, block, ctx);
+            }
+        }
+        "to_do" => {
+            if let Some(todo) = block.to_do.as_ref() {
+                let checked = if todo.checked { " checked" } else { "" };
+                let text = format!(
+                    "<input type=\"checkbox\" disabled{}> {}",
+                    checked,
+                    render_rich_text(&todo.rich_text, ctx)
+                );
+                push_list_item(out, &text, block, ctx);
+            }
+        }
+        "quote" => {
+            if let Some(container) = block.quote.as_ref() {
+                let text = render_rich_text(&container.rich_text, ctx);
+                out.push_str(&format!("<blockquote>\n<p>{}</p>\n", text));
+                out.push_str(&render_children(block, ctx));
+                out.push_str("</blockquote>\n");
+            }
+        }
+        "code" => {
+            if let Some(code) = block.code.as_ref() {
+                let class = code
+                    .language
+                    .as_deref()
+                    .map(|lang| format!(" class=\"language-{}\"", escape(lang)))
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "<pre><code{}>{}</code></pre>\n",
+                    class,
+                    escape(&plain_text(&code.rich_text))
+                ));
+            }
+        }
+        "callout" => {
+            if let Some(callout) = block.callout.as_ref() {
+                let text = render_rich_text(&callout.rich_text, ctx);
+                out.push_str(&format!("<aside class=\"callout\">\n<p>{}</p>\n", text));
+                out.push_str(&render_children(block, ctx));
+                out.push_str("</aside>\n");
+            }
+        }
+        "divider" => out.push_str("<hr>\n"),
+        "image" => {
+            if let Some(image) = block.image.as_ref()
+                && let Some(url) = image
+                    .file
+                    .as_ref()
+                    .map(|file| file.url.clone())
+                    .or_else(|| image.external.as_ref().map(|ext| ext.url.clone()))
+            {
+                let href = ctx.blob_link(build_blob_path(&block.id, None, Some(&url)), url);
+                let alt = plain_text(&image.caption);
+                out.push_str(&format!(
+                    "<figure>\n<img src=\"{}\" alt=\"{}\">\n",
+                    escape(&href),
+                    escape(&alt)
+                ));
+                push_caption(out, &image.caption, ctx);
+                out.push_str("</figure>\n");
+            }
+        }
+        "bookmark" => {
+            if let Some(bookmark) = block.bookmark.as_ref() {
+                out.push_str(&format!("<p>{}</p>\n", link(&bookmark.url, &escape(&bookmark.url))));
+            }
+        }
+        "toggle" | "template" => {
+            let container = block.toggle.as_ref().or(block.template.as_ref());
+            if let Some(container) = container {
+                let text = render_rich_text(&container.rich_text, ctx);
+                let flat = format!("<p><strong>{}</strong></p>", text);
+                push_toggle(out, &text, &flat, block, ctx);
+            }
+        }
+        "equation" => {
+            if let Some(eq) = block.equation.as_ref() {
+                out.push_str(&format!(
+                    "<div class=\"math-display\">\\[{}\\]</div>\n",
+                    escape(&eq.expression)
+                ));
+            }
+        }
+        "child_page" => {
+            if let Some(child) = block.child_page.as_ref() {
+                let (href, _) = ctx.page_link(&block.id);
+                out.push_str(&format!("<p>{}</p>\n", link(&href, &escape(&child.title))));
+            }
+        }
+        "child_database" => {
+            if let Some(child) = block.child_database.as_ref() {
+                out.push_str(&format!(
+                    "<p class=\"child-database\">{}</p>\n",
+                    escape(&child.title)
+                ));
+            }
+        }
+        "table" => {
+            if let Some(table) = block.table.as_ref() {
+                push_table(out, block, table.has_column_header, table.has_row_header, ctx);
+                if block.truncated {
+                    out.push_str(TRUNCATED_MARKER);
+                }
+            }
+        }
+        "file" => push_file(out, ctx, block.file.as_ref(), &block.id, None),
+        "pdf" => push_file(out, ctx, block.pdf.as_ref(), &block.id, None),
+        "video" => push_file(out, ctx, block.video.as_ref(), &block.id, Some("video")),
+        "audio" => push_file(out, ctx, block.audio.as_ref(), &block.id, Some("audio")),
+        "embed" => {
+            if let Some(embed) = block.embed.as_ref() {
+                out.push_str(&format!("<p>{}</p>\n", link(&embed.url, "Embed")));
+            }
+        }
+        "link_to_page" => {
+            if let Some(target) = block.link_to_page.as_ref() {
+                if let Some(page_id) = target.page_id.as_deref() {
+                    let (href, title) = ctx.page_link(page_id);
+                    let title = title.unwrap_or_else(|| page_id.to_string());
+                    out.push_str(&format!("<p>{}</p>\n", link(&href, &escape(&title))));
+                } else if let Some(database_id) = target.database_id.as_deref() {
+                    out.push_str(&format!(
+                        "<p>{}</p>\n",
+                        link(&notion_url(database_id), &escape(database_id))
+                    ));
+                }
+            }
+        }
+        "column_list" => match ctx.options.column_style {
+            ColumnStyle::Sequential => {
+                for column in &block.children {
+                    out.push_str(&render_children(column, ctx));
+                }
+                if block.truncated {
+                    out.push_str(TRUNCATED_MARKER);
+                }
+            }
+            ColumnStyle::Grid => {
+                out.push_str(&format!(
+                    "<div class=\"columns\" style=\"display: grid; grid-template-columns: repeat({}, 1fr); gap: 1em;\">\n",
+                    block.children.len().max(1)
+                ));
+                for column in &block.children {
+                    out.push_str("<div class=\"column\">\n");
+                    out.push_str(&render_children(column, ctx));
+                    out.push_str("</div>\n");
+                }
+                out.push_str("</div>\n");
+                if block.truncated {
+                    out.push_str(TRUNCATED_MARKER);
+                }
+            }
+        },
+        "column" | "synced_block" => out.push_str(&render_children(block, ctx)),
+        "table_of_contents" => {
+            if ctx.headings.is_empty() {
+                return;
+            }
+            let min_level = ctx.headings.iter().map(|h| h.level).min().unwrap_or(1);
+            out.push_str("<nav class=\"table-of-contents\">\n<ul>\n");
+            for heading in &ctx.headings {
+                out.push_str(&format!(
+                    "<li class=\"toc-level-{}\"><a href=\"#{}\">{}</a></li>\n",
+                    heading.level - min_level + 1,
+                    escape(&heading.anchor),
+                    escape(&heading.text)
+                ));
+            }
+            out.push_str("</ul>\n</nav>\n");
+        }
+        "link_preview" => {
+            if let Some(preview) = block.link_preview.as_ref() {
+                out.push_str(&format!("<p>{}</p>\n", link(&preview.url, &escape(&preview.url))));
+            }
+        }
+        "breadcrumb" => {}
+        _ => {}
+    }
+}
+
+fn push_heading(
+    out: &mut String,
+    level: usize,
+    heading: Option<&HeadingContainer>,
+    block: &Block,
+    ctx: &mut RenderContext,
+) {
+    let Some(heading) = heading else { return };
+    let text = render_rich_text(&heading.rich_text, ctx);
+    let id = ctx
+        .headings
+        .iter()
+        .find(|h| h.block_id == block.id)
+        .map(|h| format!(" id=\"{}\"", escape(&h.anchor)))
+        .unwrap_or_default();
+    let tag = format!("<h{level}{id}>{text}</h{level}>");
+    if heading.is_toggleable {
+        push_toggle(out, &tag, &tag, block, ctx);
+    } else {
+        out.push_str(&tag);
+        out.push('\n');
+        out.push_str(&render_children(block, ctx));
+    }
+}
+
+fn push_toggle(
+    out: &mut String,
+    summary: &str,
+    flat_summary: &str,
+    block: &Block,
+    ctx: &mut RenderContext,
+) {
+    let children = render_children(block, ctx);
+    match ctx.options.toggle_style {
+        ToggleStyle::Details => {
+            out.push_str(&format!("<details>\n<summary>{}</summary>\n", summary));
+            out.push_str(&children);
+            out.push_str("</details>\n");
+        }
+        ToggleStyle::Flatten => {
+            out.push_str(flat_summary);
+            out.push('\n');
+            out.push_str(&children);
+        }
+    }
+}
+
+fn push_list_item(out: &mut String, text: &str, block: &Block, ctx: &mut RenderContext) {
+    out.push_str("<li>");
+    out.push_str(text);
+    let children = render_children(block, ctx);
+    if !children.is_empty() {
+        out.push('\n');
+        out.push_str(&children);
+    }
+    out.push_str("</li>\n");
+}
+
+fn push_indented(out: &mut String, children: &str) {
+    if !children.is_empty() {
+        out.push_str("<div class=\"indent\">\n");
+        out.push_str(children);
+        out.push_str("</div>\n");
+    }
+}
+
+fn push_caption(out: &mut String, caption: &[RichText], ctx: &mut RenderContext) {
+    if !caption.is_empty() {
+        let text = render_rich_text(caption, ctx);
+        out.push_str(&format!("<figcaption>{}</figcaption>\n", text));
+    }
+}
+
+fn push_file(
+    out: &mut String,
+    ctx: &mut RenderContext,
+    container: Option<&FileContainer>,
+    block_id: &str,
+    media: Option<&str>,
+) {
+    let Some(file) = render_file_link(container, block_id) else {
+        return;
+    };
+    let href = escape(&ctx.blob_link(file.path, file.url));
+    out.push_str("<figure>\n");
+    match media {
+        Some(tag) => out.push_str(&format!("<{tag} controls src=\"{href}\"></{tag}>\n")),
+        None => out.push_str(&format!("<a href=\"{}\">{}</a>\n", href, escape(&file.label))),
+    }
+    if let Some(container) = container {
+        push_caption(out, &container.caption, ctx);
+    }
+    out.push_str("</figure>\n");
+}
+
+fn push_table(
+    out: &mut String,
+    block: &Block,
+    has_column_header: bool,
+    has_row_header: bool,
+    ctx: &mut RenderContext,
+) {
+    let rows = block
+        .children
+        .iter()
+        .filter_map(|row| row.table_row.as_ref())
+        .map(|row| {
+            row.cells
+                .iter()
+                .map(|cell| render_rich_text(cell, ctx))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        return;
+    }
+
+    out.push_str("<table>\n");
+    let mut body = rows.as_slice();
+    if has_column_header {
+        out.push_str("<thead>\n<tr>");
+        for cell in &rows[0] {
+            out.push_str(&format!("<th scope=\"col\">{}</th>", cell));
+        }
+        out.push_str("</tr>\n</thead>\n");
+        body = &rows[1..];
+    }
+    out.push_str("<tbody>\n");
+    for row in body {
+        out.push_str("<tr>");
+        for (index, cell) in row.iter().enumerate() {
+            if has_row_header && index == 0 {
+                out.push_str(&format!("<th scope=\"row\">{}</th>", cell));
+            } else {
+                out.push_str(&format!("<td>{}</td>", cell));
+            }
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody>\n</table>\n");
+}
+
+pub(super) fn render_rich_text(rich_text: &[RichText], ctx: &mut RenderContext) -> String {
+    rich_text
+        .iter()
+        .map(|item| render_rich_text_item(item, ctx))
+        .collect()
+}
+
+fn render_rich_text_item(item: &RichText, ctx: &mut RenderContext) -> String {
+    if item.text_type.as_deref() == Some("equation")
+        && let Some(equation) = item.equation.as_ref()
+    {
+        return format!(
+            "<span class=\"math-inline\">\\({}\\)</span>",
+            escape(&equation.expression)
+        );
+    }
+
+    let (text, href) = rich_text_target(item, ctx);
+    let mut text = escape(&text);
+    if let Some(annotations) = item.annotations.as_ref() {
+        if annotations.code {
+            text = format!("<code>{}</code>", text);
+        }
+        if annotations.bold {
+            text = format!("<strong>{}</strong>", text);
+        }
+        if annotations.italic {
+            text = format!("<em>{}</em>", text);
+        }
+        if annotations.strikethrough {
+            text = format!("<s>{}</s>", text);
+        }
+        if annotations.underline {
+            text = format!("<u>{}</u>", text);
+        }
+        if ctx.options.colors
+            && let Some(style) = annotations.color.as_deref().and_then(color_style)
+        {
+            text = format!("<span style=\"{}\">{}</span>", style, text);
+        }
+    }
+
+    match href {
+        Some(href) => link(&href, &text),
+        None => text,
+    }
+}
+
+fn link(href: &str, html: &str) -> String {
+    format!("<a href=\"{}\">{}</a>", escape(href), html)
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
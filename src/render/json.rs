@@ -0,0 +1,92 @@
+use serde_json::{json, Map, Value};
+
+use super::{build_blob_path, BlobRef, PageInput, RenderContext, Renderer};
+use crate::notion::Block;
+
+const FILE_BLOCK_TYPES: [&str; 5] = ["image", "file", "pdf", "video", "audio"];
+
+/// Lossless export of the page: metadata, raw and mapped properties, and the
+/// full block tree as returned by the API, with file URLs pointing at blobs.
+pub struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn render(&self, page: &PageInput, ctx: &mut RenderContext) -> String {
+        let metadata = page.metadata;
+        let properties = page
+            .properties()
+            .map(|(key, value)| (key.to_string(), value.to_json()))
+            .collect::<Map<_, _>>();
+        let document = json!({
+            "id": metadata.id,
+            "url": metadata.url,
+            "created_time": metadata.created_time,
+            "last_edited_time": metadata.last_edited_time,
+            "title": metadata.title,
+            "parent": {
+                "type": metadata.parent.parent_type,
+                "database_id": metadata.parent.database_id,
+                "data_source_id": metadata.parent.data_source_id,
+            },
+            "properties": properties,
+            "raw_properties": metadata.raw_properties,
+            "blocks": page.blocks.iter().map(|block| block_to_json(block, ctx)).collect::<Vec<_>>(),
+        });
+        let mut out = serde_json::to_string_pretty(&document).unwrap_or_default();
+        out.push('\n');
+        out
+    }
+}
+
+fn block_to_json(block: &Block, ctx: &mut RenderContext) -> Value {
+    let mut value = block.raw.clone();
+    if FILE_BLOCK_TYPES.contains(&block.block_type.as_str()) {
+        rewrite_blob_url(&mut value, block, ctx);
+    }
+    if let Value::Object(object) = &mut value {
+        if !block.children.is_empty() {
+            let children = block
+                .children
+                .iter()
+                .map(|child| block_to_json(child, ctx))
+                .collect();
+            object.insert("children".to_string(), Value::Array(children));
+        }
+        if block.truncated {
+            object.insert("truncated".to_string(), Value::Bool(true));
+        }
+    }
+    value
+}
+
+fn rewrite_blob_url(value: &mut Value, block: &Block, ctx: &mut RenderContext) {
+    let Some(payload) = value.get_mut(&block.block_type) else {
+        return;
+    };
+    let name = payload
+        .get("name")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    for source in ["file", "external"] {
+        let Some(Value::Object(object)) = payload.get_mut(source) else {
+            continue;
+        };
+        let Some(original) = object.get("url").and_then(Value::as_str).map(str::to_string)
+        else {
+            continue;
+        };
+        let name = name.as_deref().filter(|_| block.block_type != "image");
+        let path = build_blob_path(&block.id, name, Some(&original));
+        // Signed file URLs expire; the stored path is the stable reference.
+        object.remove("expiry_time");
+        object.insert("url".to_string(), Value::String(path.clone()));
+        ctx.blobs.push(BlobRef {
+            path,
+            url: original,
+        });
+        return;
+    }
+}
@@ -0,0 +1,760 @@
+use super::{
+    build_blob_path, color_style, html, notion_url, plain_text, render_file_link,
+    rich_text_target, PageInput, RenderContext, Renderer,
+};
+use crate::config::{ColumnStyle, ToggleStyle};
+use crate::notion::{Block, FileContainer, HeadingContainer, PropertyValue, RichText, RichTextContainer};
+use serde_yaml::{Mapping, Value as YamlValue};
+
+const TRUNCATED_MARKER: &str = "*[… nested content omitted: max_depth reached]*\n\n";
+
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn render(&self, page: &PageInput, ctx: &mut RenderContext) -> String {
+        let mut out = front_matter(page);
+        out.push_str(&render_blocks(page.blocks, ctx));
+        out
+    }
+}
+
+fn front_matter(page: &PageInput) -> String {
+    let mut front_matter = Mapping::new();
+    let mut notion_meta = Mapping::new();
+    notion_meta.insert(
+        YamlValue::String("page_id".to_string()),
+        YamlValue::String(page.metadata.id.clone()),
+    );
+    if let Some(database_id) = page.metadata.parent.database_id.as_ref() {
+        notion_meta.insert(
+            YamlValue::String("database_id".to_string()),
+            YamlValue::String(database_id.clone()),
+        );
+    }
+    front_matter.insert(YamlValue::String("_notion".to_string()), YamlValue::Mapping(notion_meta));
+    for (key, value) in page.properties() {
+        front_matter.insert(YamlValue::String(key.to_string()), property_to_yaml(value));
+    }
+    let yaml = serde_yaml::to_string(&front_matter).unwrap_or_default();
+    let yaml = yaml.strip_prefix("---\n").unwrap_or(&yaml);
+    let mut out = String::from("---\n");
+    out.push_str(yaml);
+    if !yaml.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str("---\n\n");
+    out
+}
+
+fn property_to_yaml(value: &PropertyValue) -> YamlValue {
+    match value {
+        PropertyValue::Text(value) => YamlValue::String(value.clone()),
+        PropertyValue::Number(number) => {
+            if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+                YamlValue::Number((*number as i64).into())
+            } else {
+                YamlValue::Number((*number).into())
+            }
+        }
+        PropertyValue::Bool(value) => YamlValue::Bool(*value),
+        PropertyValue::Date {
+            start,
+            end,
+            time_zone,
+        } => {
+            if end.is_none() && time_zone.is_none() {
+                return YamlValue::String(start.clone());
+            }
+            let mut map = Mapping::new();
+            map.insert(
+                YamlValue::String("start".to_string()),
+                YamlValue::String(start.clone()),
+            );
+            map.insert(
+                YamlValue::String("end".to_string()),
+                end.clone().map(YamlValue::String).unwrap_or(YamlValue::Null),
+            );
+            if let Some(time_zone) = time_zone {
+                map.insert(
+                    YamlValue::String("time_zone".to_string()),
+                    YamlValue::String(time_zone.clone()),
+                );
+            }
+            YamlValue::Mapping(map)
+        }
+        PropertyValue::List(values) => YamlValue::Sequence(
+            values
+                .iter()
+                .map(|item| YamlValue::String(item.clone()))
+                .collect(),
+        ),
+        PropertyValue::Object(value) => serde_yaml::to_value(value).unwrap_or(YamlValue::Null),
+        PropertyValue::Null => YamlValue::Null,
+    }
+}
+
+fn render_blocks(blocks: &[Block], ctx: &mut RenderContext) -> String {
+    let mut out = String::new();
+    let mut numbering = 1usize;
+    let mut in_list = false;
+
+    for block in blocks {
+        let is_list_item = is_list_item(block);
+        if in_list && !is_list_item {
+            out.push('\n');
+        }
+        if block.block_type != "numbered_list_item" {
+            numbering = 1;
+        }
+        render_block(block, &mut numbering, ctx, &mut out);
+        in_list = is_list_item;
+    }
+
+    if in_list {
+        out.push('\n');
+    }
+    out
+}
+
+fn is_list_item(block: &Block) -> bool {
+    matches!(
+        block.block_type.as_str(),
+        "bulleted_list_item" | "numbered_list_item" | "to_do"
+    )
+}
+
+fn render_children(block: &Block, ctx: &mut RenderContext) -> String {
+    let mut out = render_blocks(&block.children, ctx);
+    if block.truncated {
+        out.push_str(TRUNCATED_MARKER);
+    }
+    out
+}
+
+fn render_block(block: &Block, numbering: &mut usize, ctx: &mut RenderContext, out: &mut String) {
+    match block.block_type.as_str() {
+        "paragraph" => {
+            if let Some(container) = block.paragraph.as_ref() {
+                let text = render_rich_text(container, ctx);
+                out.push_str(&text);
+                out.push_str("\n\n");
+            }
+            out.push_str(&render_children(block, ctx));
+        }
+        "heading_1" => push_heading(out, 1, block.heading_1.as_ref(), block, ctx),
+        "heading_2" => push_heading(out, 2, block.heading_2.as_ref(), block, ctx),
+        "heading_3" => push_heading(out, 3, block.heading_3.as_ref(), block, ctx),
+        "bulleted_list_item" => {
+            if let Some(container) = block.bulleted_list_item.as_ref() {
+                let text = render_rich_text(container, ctx);
+                push_list_item(out, "- ", &text, block, ctx);
+            }
+        }
+        "numbered_list_item" => {
+            if let Some(container) = block.numbered_list_item.as_ref() {
+                let text = render_rich_text(container, ctx);
+                let marker = format!("{}. ", numbering);
+                push_list_item(out, &marker, &text, block, ctx);
+                *numbering += 1;
+            }
+        }
+        "to_do" => {
+            if let Some(todo) = block.to_do.as_ref() {
+                let mark = if todo.checked { "x" } else { " " };
+                let marker = format!("- [{}] ", mark);
+                let text = render_rich_text_vec(&todo.rich_text, ctx);
+                push_list_item(out, &marker, &text, block, ctx);
+            }
+        }
+        "quote" => {
+            if let Some(container) = block.quote.as_ref() {
+                let text = render_rich_text(container, ctx);
+                let body = join_blocks(&text, &render_children(block, ctx));
+                out.push_str(&quote_lines(&body));
+                out.push_str("\n\n");
+            }
+        }
+        "code" => {
+            if let Some(code) = block.code.as_ref() {
+                let lang = code.language.as_deref().unwrap_or("");
+                out.push_str(&format!("```{}\n", lang));
+                out.push_str(&plain_text(&code.rich_text));
+                out.push_str("\n```\n\n");
+            }
+        }
+        "callout" => {
+            if let Some(callout) = block.callout.as_ref() {
+                let text = render_rich_text_vec(&callout.rich_text, ctx);
+                let body = join_blocks(&text, &render_children(block, ctx));
+                out.push_str("> [!NOTE]\n");
+                out.push_str(&quote_lines(&body));
+                out.push_str("\n\n");
+            }
+        }
+        "divider" => {
+            out.push_str("---\n\n");
+        }
+        "image" => {
+            if let Some(image) = block.image.as_ref()
+                && let Some(url) = image
+                    .file
+                    .as_ref()
+                    .map(|file| file.url.clone())
+                    .or_else(|| image.external.as_ref().map(|ext| ext.url.clone()))
+            {
+                let href = ctx.blob_link(build_blob_path(&block.id, None, Some(&url)), url);
+                out.push_str(&format!("![]({})\n\n", href));
+            }
+        }
+        "bookmark" => {
+            if let Some(bookmark) = block.bookmark.as_ref() {
+                out.push_str(&format!("[{}]({})\n\n", bookmark.url, bookmark.url));
+            }
+        }
+        "toggle" => {
+            if let Some(container) = block.toggle.as_ref() {
+                push_toggle(out, &container.rich_text, None, block, ctx);
+            }
+        }
+        "equation" => {
+            if let Some(eq) = block.equation.as_ref() {
+                out.push_str(&format!("$$\n{}\n$$\n\n", eq.expression));
+            }
+        }
+        "child_page" => {
+            if let Some(child) = block.child_page.as_ref() {
+                let (href, _) = ctx.page_link(&block.id);
+                out.push_str(&format!("[{}]({})\n\n", child.title, href));
+            }
+        }
+        "child_database" => {
+            if let Some(child) = block.child_database.as_ref() {
+                match ctx.child_index_link(&block.id) {
+                    Some(href) => {
+                        out.push_str(&format!("- [Database] [{}]({})\n\n", child.title, href));
+                    }
+                    None => out.push_str(&format!("- [Database] {}\n\n", child.title)),
+                }
+            }
+        }
+        "table" => {
+            if let Some(table) = block.table.as_ref() {
+                let mut state = TableState::new(
+                    table.table_width,
+                    table.has_column_header,
+                    table.has_row_header,
+                );
+                for row in block.children.iter().filter_map(|row| row.table_row.as_ref()) {
+                    let cells = row
+                        .cells
+                        .iter()
+                        .map(|cell| render_rich_text_vec(cell, ctx))
+                        .collect::<Vec<_>>();
+                    state.rows.push(cells);
+                }
+                flush_table(out, state);
+                if block.truncated {
+                    out.push_str(TRUNCATED_MARKER);
+                }
+            }
+        }
+        "file" => push_file_link(out, ctx, block.file.as_ref(), &block.id),
+        "pdf" => push_file_link(out, ctx, block.pdf.as_ref(), &block.id),
+        "video" => push_file_link(out, ctx, block.video.as_ref(), &block.id),
+        "audio" => push_file_link(out, ctx, block.audio.as_ref(), &block.id),
+        "embed" => {
+            if let Some(embed) = block.embed.as_ref() {
+                out.push_str(&format!("[Embed]({})\n\n", embed.url));
+            }
+        }
+        "link_to_page" => {
+            if let Some(link) = block.link_to_page.as_ref() {
+                if let Some(page_id) = link.page_id.as_deref() {
+                    let (href, title) = ctx.page_link(page_id);
+                    let title = title.unwrap_or_else(|| page_id.to_string());
+                    out.push_str(&format!("[{}]({})\n\n", title, href));
+                } else if let Some(database_id) = link.database_id.as_deref() {
+                    out.push_str(&format!("[{}]({})\n\n", database_id, notion_url(database_id)));
+                }
+            }
+        }
+        "column_list" => match ctx.options.column_style {
+            ColumnStyle::Sequential => {
+                for column in &block.children {
+                    out.push_str(&render_children(column, ctx));
+                }
+                if block.truncated {
+                    out.push_str(TRUNCATED_MARKER);
+                }
+            }
+            ColumnStyle::Grid => {
+                out.push_str(&format!(
+                    "<div style=\"display: grid; grid-template-columns: repeat({}, 1fr); gap: 1em;\">\n\n",
+                    block.children.len().max(1)
+                ));
+                for column in &block.children {
+                    out.push_str("<div>\n\n");
+                    let children = render_children(column, ctx);
+                    let children = children.trim_end_matches('\n');
+                    if !children.is_empty() {
+                        out.push_str(children);
+                        out.push_str("\n\n");
+                    }
+                    out.push_str("</div>\n\n");
+                }
+                out.push_str("</div>\n\n");
+                if block.truncated {
+                    out.push_str(TRUNCATED_MARKER);
+                }
+            }
+        },
+        "column" | "synced_block" => {
+            out.push_str(&render_children(block, ctx));
+        }
+        "template" => {
+            if let Some(container) = block.template.as_ref() {
+                push_toggle(out, &container.rich_text, None, block, ctx);
+            }
+        }
+        "table_of_contents" => {
+            let min_level = ctx.headings.iter().map(|h| h.level).min().unwrap_or(1);
+            for heading in &ctx.headings {
+                out.push_str(&format!(
+                    "{}- [{}](#{})\n",
+                    "  ".repeat(heading.level - min_level),
+                    heading.text,
+                    heading.anchor
+                ));
+            }
+            if !ctx.headings.is_empty() {
+                out.push('\n');
+            }
+        }
+        "link_preview" => {
+            if let Some(preview) = block.link_preview.as_ref() {
+                out.push_str(&format!("[{}]({})\n\n", preview.url, preview.url));
+            }
+        }
+        "breadcrumb" => {}
+        _ => {}
+    }
+}
+
+fn push_heading(
+    out: &mut String,
+    level: usize,
+    heading: Option<&HeadingContainer>,
+    block: &Block,
+    ctx: &mut RenderContext,
+) {
+    let Some(heading) = heading else { return };
+    if heading.is_toggleable {
+        push_toggle(out, &heading.rich_text, Some(level), block, ctx);
+    } else {
+        let text = render_rich_text_vec(&heading.rich_text, ctx);
+        out.push_str(&format!("{} {}\n\n", "#".repeat(level), text));
+        out.push_str(&render_children(block, ctx));
+    }
+}
+
+/// A toggle, or a toggleable heading at `level`. Markdown isn't parsed inside
+/// `<summary>`, so the summary is rendered as HTML.
+fn push_toggle(
+    out: &mut String,
+    rich_text: &[RichText],
+    level: Option<usize>,
+    block: &Block,
+    ctx: &mut RenderContext,
+) {
+    let children = render_children(block, ctx);
+    match ctx.options.toggle_style {
+        ToggleStyle::Details => {
+            let text = html::render_rich_text(rich_text, ctx);
+            out.push_str("<details>\n<summary>");
+            match level {
+                Some(level) => out.push_str(&format!("<h{level}>{text}</h{level}>")),
+                None => out.push_str(&text),
+            }
+            out.push_str("</summary>\n\n");
+            let children = children.trim_end_matches('\n');
+            if !children.is_empty() {
+                out.push_str(children);
+                out.push_str("\n\n");
+            }
+            out.push_str("</details>\n\n");
+        }
+        ToggleStyle::Flatten => {
+            let text = render_rich_text_vec(rich_text, ctx);
+            match level {
+                Some(level) => out.push_str(&format!("{} {}", "#".repeat(level), text)),
+                None => out.push_str(&format!("**{}**", text)),
+            }
+            out.push_str("\n\n");
+            out.push_str(&children);
+        }
+    }
+}
+
+fn push_list_item(
+    out: &mut String,
+    marker: &str,
+    text: &str,
+    block: &Block,
+    ctx: &mut RenderContext,
+) {
+    out.push_str(marker);
+    out.push_str(text);
+    out.push('\n');
+    let children = render_children(block, ctx);
+    let children = indent_lines(&children, &" ".repeat(marker.chars().count()));
+    if children.is_empty() {
+        return;
+    }
+    if block.children.first().is_none_or(|child| !is_list_item(child)) {
+        out.push('\n');
+    }
+    out.push_str(&children);
+    out.push('\n');
+}
+
+fn push_file_link(
+    out: &mut String,
+    ctx: &mut RenderContext,
+    container: Option<&FileContainer>,
+    block_id: &str,
+) {
+    if let Some(link) = render_file_link(container, block_id) {
+        let href = ctx.blob_link(link.path, link.url);
+        out.push_str(&format!("[{}]({})\n\n", link.label, href));
+    }
+}
+
+fn join_blocks(text: &str, children: &str) -> String {
+    let children = children.trim_end_matches('\n');
+    if text.is_empty() {
+        children.to_string()
+    } else if children.is_empty() {
+        text.to_string()
+    } else {
+        format!("{}\n\n{}", text, children)
+    }
+}
+
+fn indent_lines(text: &str, prefix: &str) -> String {
+    text.trim_end_matches('\n')
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn quote_lines(text: &str) -> String {
+    text.trim_end_matches('\n')
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_rich_text(container: &RichTextContainer, ctx: &mut RenderContext) -> String {
+    render_rich_text_vec(&container.rich_text, ctx)
+}
+
+fn render_rich_text_vec(rich_text: &[RichText], ctx: &mut RenderContext) -> String {
+    rich_text
+        .iter()
+        .map(|item| render_rich_text_item(item, ctx))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn render_rich_text_item(item: &RichText, ctx: &mut RenderContext) -> String {
+    if item.text_type.as_deref() == Some("equation")
+        && let Some(equation) = item.equation.as_ref()
+    {
+        return format!("${}$", equation.expression);
+    }
+
+    let (mut text, href) = rich_text_target(item, ctx);
+
+    if let Some(annotations) = item.annotations.as_ref() {
+        if annotations.code {
+            text = format!("`{}`", text);
+        } else {
+            if annotations.bold {
+                text = format!("**{}**", text);
+            }
+            if annotations.italic {
+                text = format!("*{}*", text);
+            }
+            if annotations.strikethrough {
+                text = format!("~~{}~~", text);
+            }
+            if annotations.underline {
+                text = format!("<u>{}</u>", text);
+            }
+        }
+        if ctx.options.colors
+            && let Some(style) = annotations.color.as_deref().and_then(color_style)
+        {
+            text = format!("<span style=\"{}\">{}</span>", style, text);
+        }
+    }
+
+    if let Some(link) = href {
+        text = format!("[{}]({})", text, link);
+    }
+
+    text
+}
+
+struct TableState {
+    rows: Vec<Vec<String>>,
+    width: usize,
+    has_column_header: bool,
+    has_row_header: bool,
+}
+
+impl TableState {
+    fn new(width: usize, has_column_header: bool, has_row_header: bool) -> Self {
+        Self {
+            rows: Vec::new(),
+            width,
+            has_column_header,
+            has_row_header,
+        }
+    }
+}
+
+fn flush_table(out: &mut String, state: TableState) {
+    if state.rows.is_empty() {
+        return;
+    }
+
+    let width = state
+        .rows
+        .iter()
+        .map(|row| row.len())
+        .max()
+        .unwrap_or(state.width)
+        .max(state.width);
+
+    let mut rows = state.rows;
+    for row in rows.iter_mut() {
+        while row.len() < width {
+            row.push(String::new());
+        }
+    }
+
+    let (header, body_start) = if state.has_column_header {
+        (rows[0].clone(), 1)
+    } else {
+        (vec![String::new(); width], 0)
+    };
+
+    let header = header
+        .into_iter()
+        .map(|cell| if cell.is_empty() { " ".to_string() } else { cell })
+        .collect::<Vec<_>>();
+
+    out.push('|');
+    out.push_str(&header.join(" | "));
+    out.push_str(" |\n|");
+    out.push_str(&vec!["---"; width].join(" | "));
+    out.push_str(" |\n");
+
+    for row in rows.into_iter().skip(body_start) {
+        let mut row_cells = row;
+        if state.has_row_header && !row_cells.is_empty() {
+            row_cells[0] = format!("**{}**", row_cells[0]);
+        }
+        out.push('|');
+        out.push_str(&row_cells.join(" | "));
+        out.push_str(" |\n");
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::super::{collect_headings, LinkResolver, LinkTarget, OutputTarget};
+    use super::*;
+    use crate::config::RenderConfig;
+    use std::collections::BTreeMap;
+
+    struct NoLinks;
+
+    impl LinkResolver for NoLinks {
+        fn resolve(&self, _page_id: &str) -> Option<LinkTarget> {
+            None
+        }
+    }
+
+    fn block(block_type: &str, text: &str, children: Vec<Block>) -> Block {
+        let mut value = json!({
+            "id": format!("{}-{}", block_type, text),
+            "type": block_type,
+            "has_children": !children.is_empty(),
+        });
+        value[block_type] = json!({
+            "rich_text": [{ "type": "text", "plain_text": text }],
+        });
+        let mut block: Block = serde_json::from_value(value).unwrap();
+        block.children = children;
+        block
+    }
+
+    fn render(blocks: &[Block], toggle_style: ToggleStyle) -> String {
+        let options = RenderConfig {
+            toggle_style,
+            ..RenderConfig::default()
+        };
+        let output = OutputTarget::default();
+        let child_indexes = BTreeMap::new();
+        let mut ctx = RenderContext {
+            options: &options,
+            page_path: "page.md",
+            output: &output,
+            resolver: &NoLinks,
+            child_indexes: &child_indexes,
+            blobs: Vec::new(),
+            links: BTreeMap::new(),
+            headings: collect_headings(blocks),
+        };
+        render_blocks(blocks, &mut ctx)
+    }
+
+    #[test]
+    fn nested_lists_indent_by_marker_width() {
+        let blocks = vec![
+            block(
+                "bulleted_list_item",
+                "one",
+                vec![
+                    block("numbered_list_item", "a", vec![]),
+                    block("numbered_list_item", "b", vec![]),
+                ],
+            ),
+            block(
+                "numbered_list_item",
+                "two",
+                vec![block("paragraph", "body", vec![])],
+            ),
+        ];
+
+        assert_eq!(
+            render(&blocks, ToggleStyle::Details),
+            "- one\n  1. a\n  2. b\n1. two\n\n   body\n\n"
+        );
+    }
+
+    #[test]
+    fn numbering_restarts_after_other_blocks() {
+        let blocks = vec![
+            block("numbered_list_item", "a", vec![]),
+            block("numbered_list_item", "b", vec![]),
+            block("paragraph", "break", vec![]),
+            block("numbered_list_item", "c", vec![]),
+            block("bulleted_list_item", "d", vec![]),
+            block("numbered_list_item", "e", vec![]),
+        ];
+
+        assert_eq!(
+            render(&blocks, ToggleStyle::Details),
+            "1. a\n2. b\n\nbreak\n\n1. c\n- d\n1. e\n\n"
+        );
+    }
+
+    #[test]
+    fn quote_and_callout_bodies_include_children() {
+        let blocks = vec![
+            block("quote", "said", vec![block("paragraph", "more", vec![])]),
+            block("callout", "note", vec![block("bulleted_list_item", "x", vec![])]),
+        ];
+
+        assert_eq!(
+            render(&blocks, ToggleStyle::Details),
+            "> said\n>\n> more\n\n> [!NOTE]\n> note\n>\n> - x\n\n"
+        );
+    }
+
+    #[test]
+    fn toggles_render_as_details() {
+        let mut heading = block("heading_2", "title", vec![block("paragraph", "inside", vec![])]);
+        if let Some(container) = heading.heading_2.as_mut() {
+            container.is_toggleable = true;
+        }
+        let blocks = vec![
+            block("toggle", "summary", vec![block("paragraph", "hidden", vec![])]),
+            block("toggle", "empty", vec![]),
+            heading,
+        ];
+
+        assert_eq!(
+            render(&blocks, ToggleStyle::Details),
+            "<details>\n<summary>summary</summary>\n\nhidden\n\n</details>\n\n\
+             <details>\n<summary>empty</summary>\n\n</details>\n\n\
+             <details>\n<summary><h2>title</h2></summary>\n\ninside\n\n</details>\n\n"
+        );
+    }
+
+    #[test]
+    fn flattened_toggles_keep_their_children() {
+        let blocks = vec![block(
+            "toggle",
+            "summary",
+            vec![block("paragraph", "hidden", vec![])],
+        )];
+
+        assert_eq!(render(&blocks, ToggleStyle::Flatten), "**summary**\n\nhidden\n\n");
+    }
+
+    #[test]
+    fn properties_map_to_typed_yaml() {
+        let yaml = |value: PropertyValue| serde_yaml::to_string(&property_to_yaml(&value)).unwrap();
+
+        assert_eq!(yaml(PropertyValue::Text("hello".to_string())), "hello\n");
+        assert_eq!(yaml(PropertyValue::Number(3.0)), "3\n");
+        assert_eq!(yaml(PropertyValue::Number(2.5)), "2.5\n");
+        assert_eq!(yaml(PropertyValue::Bool(true)), "true\n");
+        assert_eq!(yaml(PropertyValue::Null), "null\n");
+        assert_eq!(
+            yaml(PropertyValue::List(vec!["a".to_string(), "b".to_string()])),
+            "- a\n- b\n"
+        );
+        assert_eq!(
+            yaml(PropertyValue::Date {
+                start: "2024-01-02".to_string(),
+                end: None,
+                time_zone: None,
+            }),
+            "2024-01-02\n"
+        );
+        assert_eq!(
+            yaml(PropertyValue::Date {
+                start: "2024-01-02".to_string(),
+                end: None,
+                time_zone: Some("Europe/Berlin".to_string()),
+            }),
+            "start: 2024-01-02\nend: null\ntime_zone: Europe/Berlin\n"
+        );
+    }
+}
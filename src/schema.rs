@@ -0,0 +1,167 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use log::warn;
+
+use crate::config::DatabaseConfig;
+use crate::notion::{DataSourceInfo, DataSourceSchema, PropertyValue};
+use crate::plan::{write_file, EntryKind};
+use crate::sqlite::RowTable;
+use crate::template::with_suffix;
+use crate::{AppState, DatabaseState};
+
+/// `schema.json` in the directory the database's pages are written under,
+/// suffixed with the data source id when the database has several.
+pub fn schema_path(database: &DatabaseState, data_source_id: &str) -> String {
+    let root = database.path_template.root();
+    let path = if root.is_empty() {
+        "schema.json".to_string()
+    } else {
+        format!("{}/schema.json", root)
+    };
+    if database.data_sources.len() > 1 {
+        with_suffix(&path, data_source_id)
+    } else {
+        path
+    }
+}
+
+/// Re-fetches the schema of each data source so property changes made since
+/// startup reach exports, SQLite and value types, and publishes the updated
+/// database for webhook syncs.
+pub async fn refresh_schemas(state: &AppState, database: &DatabaseState) -> DatabaseState {
+    let mut refreshed = database.clone();
+    for data_source in &mut refreshed.data_sources {
+        match state.notion.fetch_data_source_schema(&data_source.id).await {
+            Ok(schema) => data_source.schema = Some(schema),
+            Err(err) => warn!(
+                "failed to refresh schema for data source {} (db {}): {err}",
+                data_source.id, database.id
+            ),
+        }
+    }
+    if refreshed.data_sources.is_empty() {
+        return refreshed;
+    }
+    if let Some(sqlite) = state.sqlite.as_ref() {
+        let tables = refreshed
+            .data_sources
+            .iter()
+            .map(|data_source| {
+                RowTable::new(
+                    data_source,
+                    &refreshed.property_map,
+                    refreshed.property_includes.as_ref(),
+                )
+            })
+            .collect();
+        if let Err(err) = sqlite.register_data_sources(&refreshed.id, tables).await {
+            warn!("failed to update sqlite tables (db {}): {err:#}", database.id);
+        }
+    }
+
+    let mut databases = state
+        .databases
+        .write()
+        .unwrap_or_else(|err| err.into_inner());
+    let mut updated = databases.as_ref().clone();
+    if let Some(slot) = updated.iter_mut().find(|slot| slot.id == refreshed.id) {
+        *slot = refreshed.clone();
+    }
+    *databases = Arc::new(updated);
+    refreshed
+}
+
+pub async fn export_schema(
+    state: &AppState,
+    database: &DatabaseState,
+    data_source: &DataSourceInfo,
+) {
+    let Some(schema) = data_source.schema.as_ref() else {
+        return;
+    };
+    let body = match serde_json::to_vec_pretty(schema) {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("failed to serialize schema for data source {}: {err}", data_source.id);
+            return;
+        }
+    };
+    let path = schema_path(database, &data_source.id);
+    for storage in &database.storage {
+        let result =
+            write_file(state, database, storage, EntryKind::Schema, &path, body.clone()).await;
+        if let Err(err) = result {
+            warn!(
+                "failed to write schema {} on {} (db {}): {err}",
+                path, storage.name, database.id
+            );
+        }
+    }
+}
+
+/// Fills in values the page payload leaves out and fixes their types, so
+/// an empty multi-select renders as `[]` rather than `null` or nothing.
+pub fn apply_schema(properties: &mut BTreeMap<String, PropertyValue>, schema: &DataSourceSchema) {
+    for (name, property) in &schema.properties {
+        let value = properties.remove(name).unwrap_or(PropertyValue::Null);
+        let value = match (property.property_type.as_str(), value) {
+            ("multi_select" | "people" | "relation" | "files", PropertyValue::Null) => {
+                PropertyValue::List(Vec::new())
+            }
+            ("checkbox", PropertyValue::Null) => PropertyValue::Bool(false),
+            ("number", PropertyValue::Text(text)) => match text.trim().parse() {
+                Ok(number) => PropertyValue::Number(number),
+                Err(_) => PropertyValue::Text(text),
+            },
+            (_, value) => value,
+        };
+        properties.insert(name.clone(), value);
+    }
+}
+
+/// Warns about property names in the database config that none of its data
+/// sources define. Transforms may also refer to mapped names.
+pub fn validate_config(
+    db: &DatabaseConfig,
+    property_map: &BTreeMap<String, String>,
+    data_sources: &[DataSourceInfo],
+) {
+    let schemas = data_sources
+        .iter()
+        .filter_map(|data_source| data_source.schema.as_ref())
+        .collect::<Vec<_>>();
+    if schemas.is_empty() {
+        return;
+    }
+    let known = schemas
+        .iter()
+        .flat_map(|schema| schema.properties.keys())
+        .map(String::as_str)
+        .collect::<HashSet<_>>();
+
+    for name in property_map.keys() {
+        if !known.contains(name.as_str()) {
+            warn!("database {}: properties.map refers to unknown property '{}'", db.id, name);
+        }
+    }
+    for name in db.properties.filter.includes.iter().flatten() {
+        if !known.contains(name.as_str()) {
+            warn!(
+                "database {}: properties.filter.includes refers to unknown property '{}'",
+                db.id, name
+            );
+        }
+    }
+    for name in db.properties.transform.keys() {
+        let mapped = property_map
+            .iter()
+            .any(|(original, mapped)| mapped == name && known.contains(original.as_str()));
+        if !known.contains(name.as_str()) && !mapped {
+            warn!(
+                "database {}: properties.transform refers to unknown property '{}'",
+                db.id, name
+            );
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde_json::Value;
+
+use crate::notion::{Block, DataSourceInfo, PageMetadata};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS data_sources (
+    id TEXT PRIMARY KEY,
+    database_id TEXT NOT NULL,
+    name TEXT,
+    table_name TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS pages (
+    id TEXT PRIMARY KEY,
+    database_id TEXT,
+    data_source_id TEXT,
+    title TEXT,
+    url TEXT,
+    created_time TEXT,
+    last_edited_time TEXT,
+    path TEXT,
+    markdown TEXT,
+    properties TEXT
+);
+CREATE TABLE IF NOT EXISTS blocks (
+    id TEXT NOT NULL,
+    page_id TEXT NOT NULL,
+    parent_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    depth INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    has_children INTEGER NOT NULL,
+    plain_text TEXT,
+    raw TEXT NOT NULL,
+    PRIMARY KEY (page_id, id)
+);
+CREATE INDEX IF NOT EXISTS blocks_parent ON blocks (page_id, parent_id, position);
+";
+
+const FIXED_COLUMNS: [&str; 4] = ["id", "url", "created_time", "last_edited_time"];
+
+/// Workspace-wide SQLite mirror, updated page by page as `sync_page` runs.
+/// Queries run on the blocking pool so they never stall the sync tasks.
+#[derive(Clone)]
+pub struct SqliteExport {
+    conn: Arc<Mutex<Connection>>,
+}
+
+pub struct PageRecord<'a> {
+    pub metadata: &'a PageMetadata,
+    pub properties: &'a BTreeMap<String, Value>,
+    pub path: &'a str,
+    pub markdown: Option<&'a str>,
+    pub blocks: &'a [Block],
+}
+
+/// A data source's row table: one column per synced property, named and
+/// typed from the data source schema.
+pub struct RowTable {
+    pub data_source_id: String,
+    pub name: Option<String>,
+    pub columns: Vec<(String, &'static str)>,
+}
+
+impl RowTable {
+    /// Columns for the schema's properties that pass the include filter,
+    /// under their mapped names.
+    pub fn new(
+        data_source: &DataSourceInfo,
+        key_map: &BTreeMap<String, String>,
+        includes: Option<&HashSet<String>>,
+    ) -> Self {
+        let mut columns: Vec<(String, &'static str)> = Vec::new();
+        let properties = data_source
+            .schema
+            .iter()
+            .flat_map(|schema| schema.properties.iter())
+            .filter(|(key, _)| includes.is_none_or(|includes| includes.contains(*key)));
+        for (key, property) in properties {
+            let name = key_map.get(key).unwrap_or(key);
+            let taken = FIXED_COLUMNS.contains(&name.to_lowercase().as_str())
+                || columns
+                    .iter()
+                    .any(|(column, _)| column.eq_ignore_ascii_case(name));
+            if name.is_empty() || taken {
+                continue;
+            }
+            columns.push((name.clone(), column_type(&property.property_type)));
+        }
+        Self {
+            data_source_id: data_source.id.clone(),
+            name: data_source.name.clone(),
+            columns,
+        }
+    }
+}
+
+/// Everything `upsert_page` writes, owned so it can move to the blocking pool.
+struct PageRow {
+    id: String,
+    database_id: Option<String>,
+    data_source_id: Option<String>,
+    title: Option<String>,
+    url: String,
+    created_time: String,
+    last_edited_time: String,
+    path: String,
+    markdown: Option<String>,
+    properties: String,
+    values: Vec<(String, SqlValue)>,
+    blocks: Vec<BlockRow>,
+}
+
+struct BlockRow {
+    id: String,
+    parent_id: String,
+    position: usize,
+    depth: usize,
+    block_type: String,
+    has_children: bool,
+    plain_text: Option<String>,
+    raw: String,
+}
+
+impl SqliteExport {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn =
+            Connection::open(path).with_context(|| format!("failed to open sqlite {path}"))?;
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("failed to initialize sqlite {path}"))?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    async fn run<T, F>(&self, task: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap_or_else(|err| err.into_inner());
+            task(&mut conn)
+        })
+        .await
+        .context("sqlite task panicked")?
+    }
+
+    /// Records the data sources and creates or extends their row tables to
+    /// match the schema.
+    pub async fn register_data_sources(&self, database_id: &str, tables: Vec<RowTable>) -> Result<()> {
+        let database_id = database_id.to_string();
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            for table in &tables {
+                tx.execute(
+                    "INSERT OR REPLACE INTO data_sources (id, database_id, name, table_name) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        table.data_source_id,
+                        database_id,
+                        table.name,
+                        table_name(&table.data_source_id)
+                    ],
+                )?;
+                create_row_table(&tx, table)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Whether the mirror holds the page as of `last_edited_time`, so pages
+    /// synced before SQLite was configured get backfilled.
+    pub async fn is_page_current(&self, page_id: &str, last_edited_time: &str) -> Result<bool> {
+        let page_id = page_id.to_string();
+        let last_edited_time = last_edited_time.to_string();
+        self.run(move |conn| {
+            let recorded = conn
+                .query_row(
+                    "SELECT last_edited_time FROM pages WHERE id = ?1",
+                    params![page_id],
+                    |row| row.get::<_, Option<String>>(0),
+                )
+                .optional()?
+                .flatten();
+            Ok(recorded.as_deref() == Some(last_edited_time.as_str()))
+        })
+        .await
+    }
+
+    pub async fn upsert_page(&self, page: &PageRecord<'_>) -> Result<()> {
+        let row = PageRow::new(page)?;
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT OR REPLACE INTO pages (id, database_id, data_source_id, title, url, created_time, last_edited_time, path, markdown, properties)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    row.id,
+                    row.database_id,
+                    row.data_source_id,
+                    row.title,
+                    row.url,
+                    row.created_time,
+                    row.last_edited_time,
+                    row.path,
+                    row.markdown,
+                    row.properties,
+                ],
+            )?;
+
+            tx.execute("DELETE FROM blocks WHERE page_id = ?1", params![row.id])?;
+            for block in &row.blocks {
+                tx.execute(
+                    "INSERT OR REPLACE INTO blocks (id, page_id, parent_id, position, depth, type, has_children, plain_text, raw)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        block.id,
+                        row.id,
+                        block.parent_id,
+                        block.position as i64,
+                        block.depth as i64,
+                        block.block_type,
+                        block.has_children,
+                        block.plain_text,
+                        block.raw,
+                    ],
+                )?;
+            }
+
+            if let Some(data_source_id) = row.data_source_id.as_deref() {
+                upsert_row(&tx, data_source_id, &row)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn remove_page(&self, page_id: &str) -> Result<()> {
+        let page_id = page_id.to_string();
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            let data_source_id = tx
+                .query_row(
+                    "SELECT data_source_id FROM pages WHERE id = ?1",
+                    params![page_id],
+                    |row| row.get::<_, Option<String>>(0),
+                )
+                .ok()
+                .flatten();
+            if let Some(data_source_id) = data_source_id
+                && table_columns(&tx, &data_source_id)?.is_some()
+            {
+                tx.execute(
+                    &format!("DELETE FROM {} WHERE id = ?1", quote(&table_name(&data_source_id))),
+                    params![page_id],
+                )?;
+            }
+            tx.execute("DELETE FROM blocks WHERE page_id = ?1", params![page_id])?;
+            tx.execute("DELETE FROM pages WHERE id = ?1", params![page_id])?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl PageRow {
+    fn new(page: &PageRecord) -> Result<Self> {
+        let metadata = page.metadata;
+        let values = page
+            .properties
+            .iter()
+            .map(|(key, value)| (key.clone(), sql_value(value)))
+            .collect();
+        let mut blocks = Vec::new();
+        flatten_blocks(&mut blocks, &metadata.id, page.blocks, 0);
+        Ok(Self {
+            id: metadata.id.clone(),
+            database_id: metadata.parent.database_id.clone(),
+            data_source_id: metadata.parent.data_source_id.clone(),
+            title: metadata.title.clone(),
+            url: metadata.url.clone(),
+            created_time: metadata.created_time.clone(),
+            last_edited_time: metadata.last_edited_time.clone(),
+            path: page.path.to_string(),
+            markdown: page.markdown.map(str::to_string),
+            properties: serde_json::to_string(page.properties)?,
+            values,
+            blocks,
+        })
+    }
+}
+
+fn flatten_blocks(out: &mut Vec<BlockRow>, parent_id: &str, blocks: &[Block], depth: usize) {
+    for (position, block) in blocks.iter().enumerate() {
+        out.push(BlockRow {
+            id: block.id.clone(),
+            parent_id: parent_id.to_string(),
+            position,
+            depth,
+            block_type: block.block_type.clone(),
+            has_children: block.has_children,
+            plain_text: block_plain_text(block),
+            raw: block.raw.to_string(),
+        });
+        flatten_blocks(out, &block.id, &block.children, depth + 1);
+    }
+}
+
+fn block_plain_text(block: &Block) -> Option<String> {
+    let rich_text = block
+        .raw
+        .get(&block.block_type)?
+        .get("rich_text")?
+        .as_array()?;
+    Some(
+        rich_text
+            .iter()
+            .filter_map(|item| item.get("plain_text").and_then(Value::as_str))
+            .collect(),
+    )
+}
+
+/// Writes the page's row, filling the columns its data source registered.
+/// Properties without a column are left out rather than altering the table.
+fn upsert_row(tx: &Transaction, data_source_id: &str, row: &PageRow) -> Result<()> {
+    let Some(columns) = table_columns(tx, data_source_id)? else {
+        return Ok(());
+    };
+    let mut names = FIXED_COLUMNS.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+    let mut values = vec![
+        SqlValue::Text(row.id.clone()),
+        SqlValue::Text(row.url.clone()),
+        SqlValue::Text(row.created_time.clone()),
+        SqlValue::Text(row.last_edited_time.clone()),
+    ];
+    for (key, value) in &row.values {
+        let Some(column) = columns.get(&key.to_lowercase()) else {
+            continue;
+        };
+        if names.contains(column) {
+            continue;
+        }
+        names.push(column.clone());
+        values.push(value.clone());
+    }
+
+    let sql = format!(
+        "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+        quote(&table_name(data_source_id)),
+        names.iter().map(|name| quote(name)).collect::<Vec<_>>().join(", "),
+        (1..=names.len())
+            .map(|index| format!("?{}", index))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    tx.execute(&sql, rusqlite::params_from_iter(values))?;
+    Ok(())
+}
+
+/// Creates the row table, adding any schema columns an older table lacks.
+fn create_row_table(tx: &Transaction, table: &RowTable) -> Result<()> {
+    let name = quote(&table_name(&table.data_source_id));
+    let mut definitions = FIXED_COLUMNS
+        .iter()
+        .map(|column| {
+            let primary = if *column == "id" { " PRIMARY KEY" } else { "" };
+            format!("{} TEXT{}", column, primary)
+        })
+        .collect::<Vec<_>>();
+    definitions.extend(
+        table
+            .columns
+            .iter()
+            .map(|(column, column_type)| format!("{} {}", quote(column), column_type)),
+    );
+    tx.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {} ({})",
+        name,
+        definitions.join(", ")
+    ))?;
+    let existing = table_columns(tx, &table.data_source_id)?.unwrap_or_default();
+    for (column, column_type) in &table.columns {
+        if !existing.contains_key(&column.to_lowercase()) {
+            tx.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                name,
+                quote(column),
+                column_type
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// Columns of the data source's row table keyed by their lowercased name,
+/// since SQLite column names are case-insensitive, or `None` if the table
+/// was never registered.
+fn table_columns(
+    tx: &Transaction,
+    data_source_id: &str,
+) -> Result<Option<BTreeMap<String, String>>> {
+    let mut stmt = tx.prepare(&format!(
+        "PRAGMA table_info({})",
+        quote(&table_name(data_source_id))
+    ))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if columns.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        columns
+            .into_iter()
+            .map(|column| (column.to_lowercase(), column))
+            .collect(),
+    ))
+}
+
+fn table_name(data_source_id: &str) -> String {
+    format!("ds_{}", data_source_id.replace('-', ""))
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// SQLite column type for a Notion property type.
+fn column_type(property_type: &str) -> &'static str {
+    match property_type {
+        "number" => "REAL",
+        "checkbox" => "INTEGER",
+        _ => "TEXT",
+    }
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(value) => SqlValue::Integer(*value as i64),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => SqlValue::Integer(number),
+            None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => SqlValue::Text(text.clone()),
+        Value::Object(object) if object.contains_key("start") => {
+            let start = object.get("start").and_then(Value::as_str).unwrap_or_default();
+            match object.get("end").and_then(Value::as_str) {
+                Some(end) => SqlValue::Text(format!("{}..{}", start, end)),
+                None => SqlValue::Text(start.to_string()),
+            }
+        }
+        other => SqlValue::Text(other.to_string()),
+    }
+}
@@ -1,9 +1,64 @@
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Result};
-use opendal::{Operator, Scheme};
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use opendal::{ErrorKind, Operator, Scheme};
 
-use crate::config::BackendConfig;
+use crate::config::{BackendConfig, DeletedPagesMode};
+use crate::manifest::{
+    load_legacy_manifest, load_manifest, manifest_path, save_manifest, SyncManifest,
+};
+
+#[derive(Clone)]
+pub struct StorageBackend {
+    pub name: String,
+    pub op: Operator,
+    pub manifest_path: String,
+    pub manifest: Arc<Mutex<SyncManifest>>,
+}
+
+impl StorageBackend {
+    pub fn manifest(&self) -> std::sync::MutexGuard<'_, SyncManifest> {
+        self.manifest.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub async fn save_manifest(&self) -> Result<()> {
+        let manifest = self.manifest().clone();
+        save_manifest(&self.op, &self.manifest_path, &manifest).await
+    }
+
+    pub async fn retire(&self, path: &str, mode: DeletedPagesMode) -> Result<()> {
+        match mode {
+            DeletedPagesMode::Keep => {}
+            DeletedPagesMode::Delete => {
+                self.op
+                    .delete(path)
+                    .await
+                    .with_context(|| format!("failed to delete {path}"))?;
+            }
+            DeletedPagesMode::Archive => {
+                let data = match self.op.read(path).await {
+                    Ok(data) => data,
+                    Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+                    Err(err) => {
+                        return Err(err).with_context(|| format!("failed to read {path}"));
+                    }
+                };
+                let archive_path = format!("archive/{path}");
+                self.op
+                    .write(&archive_path, data)
+                    .await
+                    .with_context(|| format!("failed to write {archive_path}"))?;
+                self.op
+                    .delete(path)
+                    .await
+                    .with_context(|| format!("failed to delete {path}"))?;
+            }
+        }
+        Ok(())
+    }
+}
 
 pub fn init_opendal(backend: &BackendConfig) -> Result<Operator> {
     let scheme = Scheme::from_str(&backend.r#type)
@@ -12,3 +67,34 @@ pub fn init_opendal(backend: &BackendConfig) -> Result<Operator> {
     let op = Operator::via_iter(scheme, map)?;
     Ok(op)
 }
+
+/// Opens the backends of one database or page tree with its own manifest on
+/// each, seeded from the legacy shared manifest when there is none yet.
+pub async fn init_storage(
+    backends: &[BackendConfig],
+    database_id: &str,
+    data_source_ids: &[String],
+) -> Result<Vec<StorageBackend>> {
+    let manifest_path = manifest_path(database_id);
+    let mut storage = Vec::with_capacity(backends.len());
+    for backend in backends {
+        let name = backend.label();
+        let op = init_opendal(backend)?;
+        let manifest = match load_manifest(&op, &manifest_path).await {
+            Ok(Some(manifest)) => Ok(manifest),
+            Ok(None) => load_legacy_manifest(&op, data_source_ids).await,
+            Err(err) => Err(err),
+        };
+        let manifest = manifest.unwrap_or_else(|err| {
+            warn!("failed to load sync manifest from {name}, starting fresh: {err:#}");
+            SyncManifest::default()
+        });
+        storage.push(StorageBackend {
+            name,
+            op,
+            manifest_path: manifest_path.clone(),
+            manifest: Arc::new(Mutex::new(manifest)),
+        });
+    }
+    Ok(storage)
+}
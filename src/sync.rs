@@ -1,29 +1,94 @@
-use anyhow::{Context, Result};
-use std::collections::HashSet;
+use anyhow::{anyhow, Context, Result};
+use futures::{stream, StreamExt};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, Time};
 
 use log::{info, warn};
 
-use crate::render::{render_page, BlobRef};
+use crate::config::{DeletedPagesMode, OutputFormat};
+use crate::discovery::discover;
+use crate::links::{report_unresolved_links, ManifestLinks};
+use crate::manifest::{content_hash, DataSourceState, PageState};
+use crate::notion::{PageMetadata, PageParent};
+use crate::plan::{Change, DryRun, EntryKind};
+use crate::render::{BlobRef, PageInput, Rendered};
+use crate::schema::{apply_schema, export_schema, refresh_schemas};
+use crate::sqlite::PageRecord;
+use crate::storage::StorageBackend;
+use crate::table::export_tables;
+use crate::template::with_suffix;
+use crate::transform::apply_transforms;
+use crate::tree::{
+    child_index_path, children_directory, collect_children, scan_child_databases, scan_page_tree,
+    tree_page_path,
+};
 use crate::{AppState, DatabaseState};
 
 pub async fn sync_all(state: &AppState) -> Result<()> {
-    for database in &state.databases {
+    if let Some(discovery) = state.discovery.as_ref() {
+        discover(state, discovery).await;
+    }
+    let databases = state.databases();
+    let mut failed = 0usize;
+    for database in databases.iter() {
         if let Err(err) = scan_database(state, database).await {
             warn!("scan failed for database {}: {err}", database.id);
+            failed += 1;
         }
     }
+    report_unresolved_links(state);
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} databases failed to sync",
+            failed,
+            databases.len()
+        ));
+    }
     Ok(())
 }
 
 pub async fn scan_database(state: &AppState, database: &DatabaseState) -> Result<()> {
+    let result = match database.tree.as_ref() {
+        Some(tree) => scan_page_tree(state, database, tree).await,
+        None => scan_data_sources(state, &refresh_schemas(state, database).await).await,
+    };
+    // Every page has now been checked against the current settings.
+    let mut outdated = false;
+    for storage in database.storage.iter().filter(|_| result.is_ok()) {
+        let mut manifest = storage.manifest();
+        if !manifest.is_config_current(&database.config_hash) {
+            manifest.config_hash = Some(database.config_hash.clone());
+            outdated = true;
+        }
+    }
+    if outdated {
+        save_manifests(state, database).await;
+    }
+    result
+}
+
+async fn scan_data_sources(state: &AppState, database: &DatabaseState) -> Result<()> {
+    let mut failed = 0usize;
     for data_source in &database.data_sources {
+        export_schema(state, database, data_source).await;
         if let Err(err) = scan_data_source(state, database, &data_source.id).await {
             warn!(
                 "scan failed for data source {} (db {}): {err}",
                 data_source.id, database.id
             );
+            failed += 1;
         }
     }
+    if database.child_databases
+        && let Err(err) = scan_child_databases(state, database).await
+    {
+        warn!("inline database scan failed (db {}): {err}", database.id);
+        failed += 1;
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} data sources failed", failed));
+    }
     Ok(())
 }
 
@@ -32,82 +97,844 @@ pub async fn scan_data_source(
     database: &DatabaseState,
     data_source_id: &str,
 ) -> Result<()> {
-    let page_ids = state.notion.query_data_source_page_ids(data_source_id).await?;
+    let started_at = sync_cursor_now()?;
+    // A dry run reports on every page, not only those edited since the cursor.
+    let edited_since =
+        edited_since(database, data_source_id).filter(|_| state.dry_run.is_none());
+    let page_ids = state
+        .notion
+        .query_data_source_page_ids(data_source_id, edited_since.as_deref())
+        .await?;
     info!(
-        "found {} pages for data source {} (db {})",
+        "found {} pages for data source {} (db {}, edited since {})",
         page_ids.len(),
         data_source_id,
-        database.id
+        database.id,
+        edited_since.as_deref().unwrap_or("the beginning")
     );
-    for page_id in page_ids {
-        if let Err(err) = sync_page(state, database, &page_id).await {
+    let live_ids: Option<HashSet<String>> = match (state.deleted_pages, &edited_since) {
+        (DeletedPagesMode::Keep, _) => None,
+        (_, None) => Some(page_ids.iter().cloned().collect()),
+        (_, Some(_)) => Some(
+            state
+                .notion
+                .query_data_source_page_ids(data_source_id, None)
+                .await?
+                .into_iter()
+                .collect(),
+        ),
+    };
+    let queried = page_ids.iter().cloned().collect::<HashSet<_>>();
+    let failures = stream::iter(page_ids)
+        .map(|page_id| async move {
+            let result = sync_page(state, database, &page_id).await;
+            (page_id, result)
+        })
+        .buffer_unordered(database.concurrency)
+        .filter_map(|(page_id, result)| async move {
+            let err = result.err()?;
             warn!("page sync failed {} (db {}): {err}", page_id, database.id);
+            Some(page_id)
+        })
+        .count()
+        .await;
+    let relinked = relink_pages(state, database, data_source_id).await;
+    if relinked > 0 {
+        info!(
+            "re-rendered {} pages with changed link targets for data source {} (db {})",
+            relinked, data_source_id, database.id
+        );
+    }
+    let backfilled = backfill_sqlite(state, database, data_source_id, &queried).await;
+    if backfilled > 0 {
+        info!(
+            "backfilled {} pages into sqlite for data source {} (db {})",
+            backfilled, data_source_id, database.id
+        );
+    }
+    if let Some(live_ids) = live_ids {
+        reconcile_data_source(state, database, data_source_id, &live_ids).await;
+    }
+    export_tables(state, database, data_source_id).await;
+
+    if failures == 0 {
+        for storage in &database.storage {
+            storage.manifest().data_sources.insert(
+                data_source_id.to_string(),
+                DataSourceState {
+                    last_synced_at: started_at.clone(),
+                },
+            );
         }
+    } else {
+        warn!(
+            "{} pages failed for data source {} (db {}), keeping previous sync cursor",
+            failures, data_source_id, database.id
+        );
+    }
+    save_manifests(state, database).await;
+    if failures > 0 {
+        return Err(anyhow!("{} pages failed", failures));
     }
     Ok(())
 }
 
-pub async fn sync_page_by_id(state: &AppState, page_id: &str) -> Result<()> {
+/// Notion rounds `last_edited_time` down to the minute, so the cursor is
+/// truncated the same way to avoid missing edits made while a scan runs.
+fn sync_cursor_now() -> Result<String> {
+    let now = OffsetDateTime::now_utc();
+    let now = now.replace_time(Time::from_hms(now.hour(), now.minute(), 0)?);
+    Ok(now.format(&Rfc3339)?)
+}
+
+/// Oldest cursor across the backends, or none if any backend lacks one or
+/// last rendered its pages under other settings.
+fn edited_since(database: &DatabaseState, data_source_id: &str) -> Option<String> {
+    let mut since: Option<String> = None;
+    for storage in &database.storage {
+        let manifest = storage.manifest();
+        if !manifest.is_config_current(&database.config_hash) {
+            return None;
+        }
+        let last_synced_at = manifest.last_synced_at(data_source_id)?;
+        if since.as_deref().is_none_or(|current| last_synced_at < current) {
+            since = Some(last_synced_at.to_string());
+        }
+    }
+    since
+}
+
+async fn relink_pages(state: &AppState, database: &DatabaseState, data_source_id: &str) -> usize {
+    let resolver = ManifestLinks::new(state, database);
+    let mut candidates = BTreeSet::new();
+    for storage in &database.storage {
+        let pages = storage
+            .manifest()
+            .pages
+            .iter()
+            .filter(|(_, page)| page.data_source_id.as_deref() == Some(data_source_id))
+            .map(|(id, page)| (id.clone(), page.clone()))
+            .collect::<Vec<_>>();
+        candidates.extend(
+            pages
+                .into_iter()
+                .filter(|(_, page)| resolver.links_changed(page))
+                .map(|(id, _)| id),
+        );
+    }
+
+    let mut relinked = 0usize;
+    for page_id in candidates {
+        match sync_page(state, database, &page_id).await {
+            Ok(()) => relinked += 1,
+            Err(err) => warn!("page relink failed {} (db {}): {err}", page_id, database.id),
+        }
+    }
+    relinked
+}
+
+/// Re-syncs recorded pages the SQLite mirror lacks or holds an older edit
+/// of, which the edited-since query alone would never return.
+async fn backfill_sqlite(
+    state: &AppState,
+    database: &DatabaseState,
+    data_source_id: &str,
+    queried: &HashSet<String>,
+) -> usize {
+    let Some(sqlite) = state.sqlite.as_ref() else {
+        return 0;
+    };
+    let mut recorded = BTreeMap::new();
+    for storage in &database.storage {
+        recorded.extend(
+            storage
+                .manifest()
+                .pages
+                .iter()
+                .filter(|(id, page)| {
+                    page.data_source_id.as_deref() == Some(data_source_id)
+                        && !queried.contains(*id)
+                })
+                .map(|(id, page)| (id.clone(), page.last_edited_time.clone())),
+        );
+    }
+
+    let mut backfilled = 0usize;
+    for (page_id, last_edited_time) in recorded {
+        match sqlite.is_page_current(&page_id, &last_edited_time).await {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(err) => {
+                warn!("failed to check sqlite for page {} (db {}): {err:#}", page_id, database.id);
+                continue;
+            }
+        }
+        match sync_page(state, database, &page_id).await {
+            Ok(()) => backfilled += 1,
+            Err(err) => warn!("sqlite backfill failed {} (db {}): {err}", page_id, database.id),
+        }
+    }
+    backfilled
+}
+
+async fn reconcile_data_source(
+    state: &AppState,
+    database: &DatabaseState,
+    data_source_id: &str,
+    live_ids: &HashSet<String>,
+) {
+    let mut missing = BTreeSet::new();
+    for storage in &database.storage {
+        let manifest = storage.manifest();
+        missing.extend(
+            manifest
+                .pages
+                .iter()
+                .filter(|(id, page)| {
+                    page.data_source_id.as_deref() == Some(data_source_id)
+                        && !live_ids.contains(*id)
+                })
+                .map(|(id, _)| id.clone()),
+        );
+    }
+    for page_id in missing {
+        if let Err(err) = remove_page(state, database, &page_id).await {
+            warn!("page removal failed {} (db {}): {err}", page_id, database.id);
+        }
+    }
+}
+
+pub async fn remove_page_by_id(state: &AppState, page_id: &str) -> Result<()> {
+    for database in state.databases().iter() {
+        let tracked = match remove_page(state, database, page_id).await {
+            Ok(tracked) => tracked,
+            Err(err) => {
+                warn!("page removal failed {} (db {}): {err}", page_id, database.id);
+                true
+            }
+        };
+        // Databases that never had the page have nothing to rewrite.
+        if !tracked {
+            continue;
+        }
+        for data_source in &database.data_sources {
+            export_tables(state, database, &data_source.id).await;
+        }
+        save_manifests(state, database).await;
+    }
+    Ok(())
+}
+
+/// Retires the page's files and forgets it. Returns whether the database
+/// tracked the page at all, i.e. whether anything was (or in a dry run would
+/// be) removed.
+pub async fn remove_page(
+    state: &AppState,
+    database: &DatabaseState,
+    page_id: &str,
+) -> Result<bool> {
+    if state.deleted_pages == DeletedPagesMode::Keep {
+        return Ok(false);
+    }
+    let mut tracked = false;
+    let mut removed = 0usize;
+    let mut failed = BTreeSet::new();
+    for storage in &database.storage {
+        let Some(page) = storage.manifest().pages.get(page_id).cloned() else {
+            continue;
+        };
+        tracked = true;
+        let base = page.path(page_id);
+        let mut paths = database
+            .outputs
+            .iter()
+            .filter(|_| !path_taken(storage, &base, page_id))
+            .map(|output| (EntryKind::Page, output.page_path(&base)))
+            .collect::<Vec<_>>();
+        paths.extend(page.blobs.iter().map(|blob| (EntryKind::Blob, blob.clone())));
+        if database.child_databases {
+            for (id, title) in &page.child_databases {
+                let index = child_index_path(&base, &page.child_databases, id, title);
+                paths.extend(
+                    database
+                        .outputs
+                        .iter()
+                        .filter(|output| output.format == OutputFormat::Markdown)
+                        .map(|output| (EntryKind::Index, output.page_path(&index))),
+                );
+            }
+        }
+        if let Some(plan) = state.dry_run.as_ref() {
+            for (kind, path) in &paths {
+                plan.record(*kind, Change::Deleted, database, storage, path);
+            }
+            continue;
+        }
+        for (_, path) in &paths {
+            if let Err(err) = storage.retire(path, state.deleted_pages).await {
+                warn!(
+                    "failed to remove {} on {} (db {}): {err:#}",
+                    path, storage.name, database.id
+                );
+                failed.insert(storage.name.clone());
+            }
+        }
+        if !failed.contains(&storage.name) {
+            let mut manifest = storage.manifest();
+            manifest.pages.remove(page_id);
+            manifest.claims.retain(|_, holder| holder != page_id);
+            removed += 1;
+        }
+    }
+    if let Some(sqlite) = state.sqlite.as_ref()
+        && let Err(err) = sqlite.remove_page(page_id).await
+    {
+        warn!("failed to remove page {} from sqlite: {err:#}", page_id);
+    }
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "page {} removal failed on storage {}",
+            page_id,
+            failed.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if removed > 0 {
+        info!(
+            "removed page {} from {} ({:?})",
+            page_id, database.id, state.deleted_pages
+        );
+    }
+    Ok(tracked)
+}
+
+pub async fn save_manifests(state: &AppState, database: &DatabaseState) {
+    if state.dry_run.is_some() {
+        return;
+    }
+    for storage in &database.storage {
+        if let Err(err) = storage.save_manifest().await {
+            warn!(
+                "failed to save sync manifest on {} (db {}): {err:#}",
+                storage.name, database.id
+            );
+        }
+    }
+}
+
+/// Syncs a page into the database or page tree it belongs to. Returns
+/// whether one was configured for it; if not, the page is skipped.
+pub async fn sync_page_by_id(state: &AppState, page_id: &str) -> Result<bool> {
     let parent = state
         .notion
         .get_page_parent(page_id)
         .await
         .with_context(|| format!("failed to resolve parent for {page_id}"))?;
 
+    let databases = state.databases();
     let data_source_id = parent.data_source_id.as_deref();
-    let database = if let Some(data_source_id) = data_source_id {
-        state
-            .databases
+    let database = find_database(&databases, &parent, page_id);
+
+    for other in databases.iter() {
+        if database.is_some_and(|database| database.id == other.id) {
+            continue;
+        }
+        let tracked = other
+            .storage
+            .iter()
+            .any(|storage| storage.manifest().pages.contains_key(page_id));
+        if tracked {
+            info!("page {} moved out of {}", page_id, other.id);
+            if let Err(err) = remove_page(state, other, page_id).await {
+                warn!("page removal failed {} (db {}): {err}", page_id, other.id);
+            }
+            save_manifests(state, other).await;
+        }
+    }
+
+    let Some(database) = database else {
+        info!("page {} parent is not configured, skipping", page_id);
+        return Ok(false);
+    };
+
+    let result = sync_page(state, database, page_id).await;
+    if let Some(data_source_id) = data_source_id {
+        export_tables(state, database, data_source_id).await;
+    }
+    save_manifests(state, database).await;
+    result.map(|_| true)
+}
+
+/// The database or page tree a page belongs to, judged by its parent.
+fn find_database<'a>(
+    databases: &'a [DatabaseState],
+    parent: &PageParent,
+    page_id: &str,
+) -> Option<&'a DatabaseState> {
+    let database = if let Some(data_source_id) = parent.data_source_id.as_deref() {
+        databases
             .iter()
             .find(|db| db.data_sources.iter().any(|ds| ds.id == data_source_id))
     } else if let Some(database_id) = parent.database_id.as_deref() {
-        state.databases.iter().find(|db| db.id == database_id)
+        databases.iter().find(|db| db.id == database_id)
     } else {
         None
     };
 
-    let Some(database) = database else {
-        info!("page {} parent is not configured, skipping", page_id);
-        return Ok(());
-    };
-
-    sync_page(state, database, page_id).await
+    // Pages under a `[[page]]` root and rows of inline databases have no
+    // configured parent; they belong to whichever root already tracks them
+    // (or, in a tree, their parent page).
+    database.or_else(|| {
+        databases.iter().find(|db| {
+            (db.tree.is_some() && db.id == page_id)
+                || db.storage.iter().any(|storage| {
+                    let manifest = storage.manifest();
+                    manifest.pages.contains_key(page_id)
+                        || db.tree.is_some()
+                            && parent
+                                .page_id
+                                .as_ref()
+                                .is_some_and(|parent| manifest.pages.contains_key(parent))
+                })
+        })
+    })
 }
 
-pub async fn sync_page(state: &AppState, database: &DatabaseState, page_id: &str) -> Result<()> {
-    let metadata = state
+/// Renders a page as Markdown with its database's settings, without writing
+/// to storage, the manifest or SQLite.
+pub async fn render_page(state: &AppState, page_id: &str) -> Result<String> {
+    let parent = state
         .notion
-        .get_page_metadata(page_id)
+        .get_page_parent(page_id)
         .await
-        .with_context(|| format!("failed to fetch page metadata for {page_id}"))?;
+        .with_context(|| format!("failed to resolve parent for {page_id}"))?;
+    let databases = state.databases();
+    let database = find_database(&databases, &parent, page_id)
+        .ok_or_else(|| anyhow!("page {} is not in a configured database or page tree", page_id))?;
+    let metadata = fetch_page_metadata(state, database, page_id).await?;
+    let page_path = base_page_path(database, &metadata, None);
     let blocks = state
         .notion
-        .fetch_blocks(page_id, state.max_depth)
+        .fetch_blocks(page_id, database.max_depth.levels())
         .await
         .with_context(|| format!("failed to fetch blocks for {page_id}"))?;
-    let rendered = render_page(
-        &metadata,
-        &blocks,
-        &database.property_map,
-        database.property_includes.as_ref(),
+    let mut child_pages = Vec::new();
+    let mut child_databases = Vec::new();
+    collect_children(&blocks, &mut child_pages, &mut child_databases);
+    let child_indexes = child_indexes(database, &page_path, &child_databases);
+    let resolver = ManifestLinks::new(state, database);
+    let page = PageInput {
+        metadata: &metadata,
+        blocks: &blocks,
+        key_map: &database.property_map,
+        property_includes: database.property_includes.as_ref(),
+        options: &database.render,
+        path: &page_path,
+        resolver: &resolver,
+        child_indexes: &child_indexes,
+    };
+    let output = database
+        .outputs
+        .iter()
+        .find(|output| output.format == OutputFormat::Markdown)
+        .cloned()
+        .unwrap_or_default();
+    Ok(output.render(&page).content)
+}
+
+pub async fn sync_page(state: &AppState, database: &DatabaseState, page_id: &str) -> Result<()> {
+    sync_page_at(state, database, page_id, None).await
+}
+
+/// Syncs a page, placing tree pages inside `directory`. Without a directory a
+/// tree page keeps its recorded path, or lands under its parent if that is
+/// already part of the tree.
+pub async fn sync_page_at(
+    state: &AppState,
+    database: &DatabaseState,
+    page_id: &str,
+    directory: Option<&str>,
+) -> Result<()> {
+    let metadata = fetch_page_metadata(state, database, page_id).await?;
+    if metadata.archived {
+        info!("page {} is archived or trashed", page_id);
+        return remove_page(state, database, page_id).await.map(|_| ());
+    }
+    let page_path = claim_page_path(
+        database,
+        page_id,
+        base_page_path(database, &metadata, directory),
     );
-    let page_path = format!("pages/{}.md", page_id);
-    database
-        .op
-        .write(&page_path, rendered.markdown)
+    let resolver = ManifestLinks::new(state, database);
+    let stale = database
+        .storage
+        .iter()
+        .filter(|storage| {
+            let (page, config_current) = {
+                let manifest = storage.manifest();
+                let config_current = manifest.is_config_current(&database.config_hash);
+                (manifest.pages.get(page_id).cloned(), config_current)
+            };
+            !config_current
+                || page.is_none_or(|page| {
+                    page.last_edited_time != metadata.last_edited_time
+                        || page.path(page_id) != page_path
+                        || resolver.links_changed(&page)
+                        || (!database.tables.is_empty() && page.created_time.is_none())
+                })
+        })
+        .collect::<Vec<_>>();
+    let sqlite_stale = match state.sqlite.as_ref() {
+        Some(sqlite) => !sqlite
+            .is_page_current(&metadata.id, &metadata.last_edited_time)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("failed to check sqlite for page {} (db {}): {err:#}", page_id, database.id);
+                true
+            }),
+        None => false,
+    };
+    if stale.is_empty() && !sqlite_stale {
+        if let Some(plan) = state.dry_run.as_ref() {
+            for storage in &database.storage {
+                plan_unchanged(plan, database, storage, page_id);
+            }
+        }
+        info!(
+            "page {} unchanged since {}, skipping",
+            page_id, metadata.last_edited_time
+        );
+        return Ok(());
+    }
+
+    let blocks = state
+        .notion
+        .fetch_blocks(page_id, database.max_depth.levels())
         .await
-        .with_context(|| format!("failed to write markdown to {page_path}"))?;
+        .with_context(|| format!("failed to fetch blocks for {page_id}"))?;
+    let mut child_pages = Vec::new();
+    let mut child_databases = Vec::new();
+    collect_children(&blocks, &mut child_pages, &mut child_databases);
+    let child_indexes = child_indexes(database, &page_path, &child_databases);
+    let page = PageInput {
+        metadata: &metadata,
+        blocks: &blocks,
+        key_map: &database.property_map,
+        property_includes: database.property_includes.as_ref(),
+        options: &database.render,
+        path: &page_path,
+        resolver: &resolver,
+        child_indexes: &child_indexes,
+    };
+    let outputs = database
+        .outputs
+        .iter()
+        .map(|output| (output.page_path(&page_path), output.render(&page)))
+        .collect::<Vec<_>>();
+    let properties = page
+        .properties()
+        .map(|(key, value)| (key.to_string(), value.to_json()))
+        .collect::<BTreeMap<_, _>>();
+    if let Some(sqlite) = state.sqlite.as_ref() {
+        let markdown = database
+            .outputs
+            .iter()
+            .zip(&outputs)
+            .find(|(output, _)| output.format == OutputFormat::Markdown)
+            .map(|(_, (_, rendered))| rendered.content.as_str());
+        let record = PageRecord {
+            metadata: &metadata,
+            properties: &properties,
+            path: &page_path,
+            markdown,
+            blocks: &blocks,
+        };
+        if let Err(err) = sqlite.upsert_page(&record).await {
+            warn!("failed to update sqlite for page {} (db {}): {err:#}", page_id, database.id);
+        }
+    }
+    let hash = rendered_hash(&outputs);
+    let targets = stale
+        .iter()
+        .copied()
+        .filter(|storage| !storage.manifest().is_content_current(page_id, &hash))
+        .collect::<Vec<_>>();
+
+    let mut failed = BTreeSet::new();
+    for storage in &targets {
+        for (path, rendered) in &outputs {
+            let result = match state.dry_run.as_ref() {
+                Some(plan) => {
+                    let content = rendered.content.as_bytes();
+                    plan.diff(EntryKind::Page, database, storage, path, content).await
+                }
+                None => storage
+                    .op
+                    .write(path, rendered.content.clone())
+                    .await
+                    .map(|_| ())
+                    .map_err(Into::into),
+            };
+            if let Err(err) = result {
+                warn!(
+                    "failed to write page to {} on {} (db {}): {err}",
+                    path, storage.name, database.id
+                );
+                failed.insert(storage.name.clone());
+            }
+        }
+    }
+    if let Some(plan) = state.dry_run.as_ref() {
+        let written = targets.iter().map(|storage| &storage.name).collect::<HashSet<_>>();
+        for storage in &database.storage {
+            if !written.contains(&storage.name) {
+                plan_unchanged(plan, database, storage, page_id);
+            }
+        }
+    }
 
-    sync_blobs(state, database, &rendered.blobs).await?;
-    info!("synced page {} into {}", page_id, database.id);
+    let blobs = outputs
+        .iter()
+        .flat_map(|(_, rendered)| rendered.blobs.iter().cloned())
+        .collect::<Vec<_>>();
+    let links = outputs
+        .iter()
+        .flat_map(|(_, rendered)| rendered.links.clone())
+        .collect::<BTreeMap<_, _>>();
+    sync_blobs(state, database, &targets, &blobs, &mut failed).await?;
+
+    for storage in &targets {
+        if failed.contains(&storage.name) {
+            continue;
+        }
+        let previous = storage
+            .manifest()
+            .pages
+            .get(page_id)
+            .map(|page| page.path(page_id));
+        let Some(previous) = previous.filter(|previous| *previous != page_path) else {
+            continue;
+        };
+        if path_taken(storage, &previous, page_id) {
+            continue;
+        }
+        for output in &database.outputs {
+            let previous = output.page_path(&previous);
+            if let Some(plan) = state.dry_run.as_ref() {
+                plan.record(EntryKind::Page, Change::Deleted, database, storage, &previous);
+                continue;
+            }
+            match storage.op.delete(&previous).await {
+                Ok(()) => info!(
+                    "renamed {} to {} on {}",
+                    previous,
+                    output.page_path(&page_path),
+                    storage.name
+                ),
+                Err(err) => warn!(
+                    "failed to remove renamed page {} on {} (db {}): {err}",
+                    previous, storage.name, database.id
+                ),
+            }
+        }
+    }
+
+    let blob_paths = blobs
+        .iter()
+        .map(|blob| blob.path.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    for storage in &stale {
+        // Dry runs update the in-memory manifest too, so tree walks and link
+        // resolution see the planned pages; it is never saved.
+        if failed.contains(&storage.name) {
+            continue;
+        }
+        storage.manifest().pages.insert(
+            page_id.to_string(),
+            PageState {
+                last_edited_time: metadata.last_edited_time.clone(),
+                content_hash: hash.clone(),
+                data_source_id: metadata.parent.data_source_id.clone(),
+                blobs: blob_paths.clone(),
+                title: metadata.title.clone(),
+                links: links.clone(),
+                path: Some(page_path.clone()),
+                url: Some(metadata.url.clone()),
+                created_time: Some(metadata.created_time.clone()),
+                properties: properties.clone(),
+                child_pages: child_pages.clone(),
+                child_databases: child_databases.clone(),
+                owner: Some(database.id.clone()),
+            },
+        );
+    }
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "page {} failed on storage {}",
+            page_id,
+            failed.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    info!(
+        "synced page {} into {} ({} of {} backends written)",
+        page_id,
+        database.id,
+        targets.len(),
+        database.storage.len()
+    );
     Ok(())
 }
 
+/// Records the page's recorded outputs and blobs on `storage` as unchanged.
+fn plan_unchanged(plan: &DryRun, database: &DatabaseState, storage: &StorageBackend, page_id: &str) {
+    let Some(page) = storage.manifest().pages.get(page_id).cloned() else {
+        return;
+    };
+    for output in &database.outputs {
+        let path = output.page_path(&page.path(page_id));
+        plan.record(EntryKind::Page, Change::Unchanged, database, storage, &path);
+    }
+    for blob in &page.blobs {
+        plan.record(EntryKind::Blob, Change::Unchanged, database, storage, blob);
+    }
+}
+
+/// Page metadata with the data source schema and property transforms applied.
+async fn fetch_page_metadata(
+    state: &AppState,
+    database: &DatabaseState,
+    page_id: &str,
+) -> Result<PageMetadata> {
+    let mut metadata = state
+        .notion
+        .get_page_metadata(page_id)
+        .await
+        .with_context(|| format!("failed to fetch page metadata for {page_id}"))?;
+    let schema = database
+        .data_sources
+        .iter()
+        .find(|data_source| metadata.parent.data_source_id.as_deref() == Some(&data_source.id))
+        .and_then(|data_source| data_source.schema.as_ref());
+    if let Some(schema) = schema {
+        apply_schema(&mut metadata.properties, schema);
+    }
+    apply_transforms(
+        &mut metadata.properties,
+        &database.property_transforms,
+        &database.property_map,
+    );
+    Ok(metadata)
+}
+
+fn child_indexes(
+    database: &DatabaseState,
+    page_path: &str,
+    child_databases: &[(String, String)],
+) -> BTreeMap<String, String> {
+    child_databases
+        .iter()
+        .filter(|_| database.child_databases)
+        .map(|(id, title)| {
+            let path = child_index_path(page_path, child_databases, id, title);
+            (id.clone(), path)
+        })
+        .collect()
+}
+
+fn base_page_path(
+    database: &DatabaseState,
+    metadata: &PageMetadata,
+    directory: Option<&str>,
+) -> String {
+    if let Some(directory) = directory {
+        return tree_page_path(directory, metadata);
+    }
+    let recorded = |id: &str| {
+        database
+            .storage
+            .iter()
+            .find_map(|storage| storage.manifest().pages.get(id).map(|page| page.path(id)))
+    };
+    let Some(tree) = database.tree.as_ref() else {
+        // Rows of inline databases stay where the last scan placed them.
+        let own_row = metadata.parent.data_source_id.as_deref().is_none_or(|id| {
+            database
+                .data_sources
+                .iter()
+                .any(|data_source| data_source.id == id)
+        });
+        return match recorded(&metadata.id).filter(|_| !own_row) {
+            Some(path) => path,
+            None => database.path_template.render(metadata, &database.property_map),
+        };
+    };
+    if let Some(path) = recorded(&metadata.id) {
+        return path;
+    }
+    match metadata.parent.page_id.as_deref().and_then(recorded) {
+        Some(parent) => tree_page_path(&children_directory(&parent), metadata),
+        None => tree_page_path(&tree.directory, metadata),
+    }
+}
+
+/// Reserves `path` for the page, or a suffixed variant when another page is
+/// recorded at or has already reserved it. The holder keeps its path, so no
+/// page's file is ever written over by a newcomer.
+fn claim_page_path(database: &DatabaseState, page_id: &str, path: String) -> String {
+    // Every manifest stays locked until the reservation is in place, so two
+    // pages synced concurrently can't both take the same path.
+    let mut manifests = database
+        .storage
+        .iter()
+        .map(|storage| storage.manifest())
+        .collect::<Vec<_>>();
+    let taken = manifests
+        .iter()
+        .any(|manifest| manifest.path_holder(&path, page_id).is_some());
+    let path = if taken {
+        with_suffix(&path, page_id)
+    } else {
+        path
+    };
+    for manifest in &mut manifests {
+        manifest.claims.retain(|_, holder| holder != page_id);
+        manifest.claims.insert(path.clone(), page_id.to_string());
+    }
+    path
+}
+
+/// Whether another page has since taken `path`, so removing this page's copy
+/// would delete theirs.
+fn path_taken(storage: &StorageBackend, path: &str, page_id: &str) -> bool {
+    storage.manifest().path_holder(path, page_id).is_some()
+}
+
+/// Hashes every rendered output together with blob sources. Signed query
+/// strings are dropped since Notion rotates them on every fetch.
+fn rendered_hash(outputs: &[(String, Rendered)]) -> String {
+    let mut content = String::new();
+    for (path, rendered) in outputs {
+        content.push_str(&format!("{}\n{}\n", path, rendered.content));
+        for blob in &rendered.blobs {
+            content.push_str(blob.url.split('?').next().unwrap_or(&blob.url));
+            content.push('\n');
+        }
+    }
+    content_hash(&content)
+}
+
 async fn sync_blobs(
     state: &AppState,
     database: &DatabaseState,
+    targets: &[&StorageBackend],
     blobs: &[BlobRef],
+    failed: &mut BTreeSet<String>,
 ) -> Result<()> {
+    if targets.is_empty() {
+        return Ok(());
+    }
     let mut seen = HashSet::new();
     for blob in blobs {
         if !seen.insert(blob.path.clone()) {
@@ -116,18 +943,34 @@ async fn sync_blobs(
         let response = state.http.get(&blob.url).send().await?;
         let status = response.status();
         if !status.is_success() {
-            return Err(anyhow::anyhow!(
+            return Err(anyhow!(
                 "failed to download blob {}: {}",
                 blob.url,
                 status
             ));
         }
         let bytes = response.bytes().await?;
-        database
-            .op
-            .write(&blob.path, bytes.to_vec())
-            .await
-            .with_context(|| format!("failed to write blob {}", blob.path))?;
+        for storage in targets {
+            let result = match state.dry_run.as_ref() {
+                Some(plan) => {
+                    plan.diff(EntryKind::Blob, database, storage, &blob.path, &bytes)
+                        .await
+                }
+                None => storage
+                    .op
+                    .write(&blob.path, bytes.clone())
+                    .await
+                    .map(|_| ())
+                    .map_err(Into::into),
+            };
+            if let Err(err) = result {
+                warn!(
+                    "failed to write blob {} on {} (db {}): {err}",
+                    blob.path, storage.name, database.id
+                );
+                failed.insert(storage.name.clone());
+            }
+        }
     }
     Ok(())
 }
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+
+use log::warn;
+use serde_json::{Map, Value};
+
+use crate::config::TableFormat;
+use crate::manifest::SyncManifest;
+use crate::plan::{write_file, EntryKind};
+use crate::{AppState, DatabaseState};
+
+const FIXED_COLUMNS: [&str; 4] = ["id", "url", "created_time", "last_edited_time"];
+
+pub fn table_path(data_source_id: &str, format: TableFormat) -> String {
+    let ext = match format {
+        TableFormat::Csv => "csv",
+        TableFormat::Jsonl => "jsonl",
+    };
+    format!("data_sources/{}/database.{}", data_source_id, ext)
+}
+
+/// Rewrites the tabular exports of a data source from the rows recorded in
+/// each backend's manifest, so incremental scans still produce full tables.
+pub async fn export_tables(state: &AppState, database: &DatabaseState, data_source_id: &str) {
+    if database.tables.is_empty() {
+        return;
+    }
+    for storage in &database.storage {
+        let rows = collect_rows(&storage.manifest(), data_source_id);
+        for format in &database.tables {
+            let path = table_path(data_source_id, *format);
+            let body = match format {
+                TableFormat::Csv => to_csv(&rows),
+                TableFormat::Jsonl => to_jsonl(&rows),
+            };
+            let result =
+                write_file(state, database, storage, EntryKind::Table, &path, body.into_bytes()).await;
+            if let Err(err) = result {
+                warn!(
+                    "failed to write table {} on {} (db {}): {err}",
+                    path, storage.name, database.id
+                );
+            }
+        }
+    }
+}
+
+fn collect_rows(manifest: &SyncManifest, data_source_id: &str) -> Vec<Map<String, Value>> {
+    manifest
+        .pages
+        .iter()
+        .filter(|(_, page)| page.data_source_id.as_deref() == Some(data_source_id))
+        .map(|(id, page)| {
+            let mut row = Map::new();
+            row.insert("id".to_string(), Value::String(id.clone()));
+            row.insert("url".to_string(), page.url.clone().into());
+            row.insert("created_time".to_string(), page.created_time.clone().into());
+            row.insert(
+                "last_edited_time".to_string(),
+                Value::String(page.last_edited_time.clone()),
+            );
+            for (key, value) in &page.properties {
+                if !FIXED_COLUMNS.contains(&key.as_str()) {
+                    row.insert(key.clone(), value.clone());
+                }
+            }
+            row
+        })
+        .collect()
+}
+
+fn to_jsonl(rows: &[Map<String, Value>]) -> String {
+    let mut out = String::new();
+    for row in rows {
+        out.push_str(&serde_json::to_string(row).unwrap_or_default());
+        out.push('\n');
+    }
+    out
+}
+
+fn to_csv(rows: &[Map<String, Value>]) -> String {
+    let properties = rows
+        .iter()
+        .flat_map(|row| row.keys())
+        .filter(|key| !FIXED_COLUMNS.contains(&key.as_str()))
+        .collect::<BTreeSet<_>>();
+    let columns = FIXED_COLUMNS
+        .iter()
+        .copied()
+        .chain(properties.into_iter().map(String::as_str))
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    push_csv_row(&mut out, columns.iter().map(|column| column.to_string()));
+    for row in rows {
+        push_csv_row(
+            &mut out,
+            columns
+                .iter()
+                .map(|column| row.get(*column).map(csv_cell).unwrap_or_default()),
+        );
+    }
+    out
+}
+
+fn push_csv_row(out: &mut String, cells: impl Iterator<Item = String>) {
+    let cells = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect::<Vec<_>>();
+    out.push_str(&cells.join(","));
+    out.push_str("\r\n");
+}
+
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(csv_cell).collect::<Vec<_>>().join(", "),
+        Value::Object(object) if object.contains_key("start") => {
+            let start = object.get("start").map(csv_cell).unwrap_or_default();
+            match object.get("end").map(csv_cell).filter(|end| !end.is_empty()) {
+                Some(end) => format!("{}..{}", start, end),
+                None => start,
+            }
+        }
+        other => other.to_string(),
+    }
+}
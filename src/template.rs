@@ -0,0 +1,266 @@
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+use crate::notion::{PageMetadata, PropertyValue};
+
+pub const DEFAULT_PATH_TEMPLATE: &str = "pages/{{id}}.md";
+
+#[derive(Clone, Debug)]
+pub struct PathTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Clone, Debug)]
+enum TemplatePart {
+    Literal(String),
+    Variable {
+        filter: Filter,
+        name: String,
+        arg: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Filter {
+    Raw,
+    Slug,
+    Date,
+}
+
+impl PathTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            let end = after
+                .find("}}")
+                .ok_or_else(|| anyhow!("unclosed '{{{{' in path template {template}"))?;
+            parts.push(parse_variable(after[..end].trim(), template)?);
+            rest = &after[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Directory every rendered path starts with: the leading literal up to
+    /// its last `/`, or empty when a variable comes first.
+    pub fn root(&self) -> String {
+        let Some(TemplatePart::Literal(text)) = self.parts.first() else {
+            return String::new();
+        };
+        let root = text.rsplit_once('/').map_or("", |(dir, _)| dir);
+        root.trim_matches('/').to_string()
+    }
+
+    pub fn render(&self, metadata: &PageMetadata, key_map: &BTreeMap<String, String>) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(text) => out.push_str(text),
+                TemplatePart::Variable { filter, name, arg } => {
+                    let value = lookup(metadata, key_map, name).unwrap_or_default();
+                    let value = match filter {
+                        Filter::Raw => value.replace('/', "-"),
+                        Filter::Slug => slugify(&value),
+                        Filter::Date => format_date(&value, arg.as_deref().unwrap_or("%Y-%m-%d"))
+                            .unwrap_or_default(),
+                    };
+                    out.push_str(&value);
+                }
+            }
+        }
+        normalize_path(&out, &metadata.id)
+    }
+}
+
+impl Default for PathTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_PATH_TEMPLATE).expect("default path template is valid")
+    }
+}
+
+fn parse_variable(expr: &str, template: &str) -> Result<TemplatePart> {
+    let (expr, arg) = match expr.split_once('|') {
+        Some((expr, arg)) => (expr.trim(), Some(arg.trim().to_string())),
+        None => (expr, None),
+    };
+    let (filter, name) = match expr.split_once(':') {
+        Some(("slug", name)) => (Filter::Slug, name),
+        Some(("date", name)) => (Filter::Date, name),
+        Some((other, _)) => {
+            return Err(anyhow!("unknown filter '{other}' in path template {template}"));
+        }
+        None => (Filter::Raw, expr),
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("empty variable in path template {template}"));
+    }
+    if arg.is_some() && filter != Filter::Date {
+        return Err(anyhow!("only date variables take a format in path template {template}"));
+    }
+    Ok(TemplatePart::Variable {
+        filter,
+        name: name.to_string(),
+        arg,
+    })
+}
+
+fn lookup(
+    metadata: &PageMetadata,
+    key_map: &BTreeMap<String, String>,
+    name: &str,
+) -> Option<String> {
+    match name {
+        "id" => return Some(metadata.id.clone()),
+        "created_time" => return Some(metadata.created_time.clone()),
+        "last_edited_time" => return Some(metadata.last_edited_time.clone()),
+        "title" if metadata.title.is_some() => return metadata.title.clone(),
+        _ => {}
+    }
+    let value = metadata.properties.get(name).or_else(|| {
+        key_map
+            .iter()
+            .find(|(_, mapped)| mapped.as_str() == name)
+            .and_then(|(original, _)| metadata.properties.get(original))
+    })?;
+    match value {
+        PropertyValue::List(items) => Some(items.join("-")),
+        PropertyValue::Date { start, .. } => Some(start.clone()),
+        other => other.as_text(),
+    }
+}
+
+pub fn slugify(value: &str) -> String {
+    let mut out = String::new();
+    let mut pending_dash = false;
+    for c in value.chars() {
+        if c.is_alphanumeric() {
+            if pending_dash && !out.is_empty() {
+                out.push('-');
+            }
+            pending_dash = false;
+            out.extend(c.to_lowercase());
+        } else {
+            pending_dash = true;
+        }
+    }
+    out
+}
+
+pub fn format_date(value: &str, format: &str) -> Option<String> {
+    let value = value.split("..").next().unwrap_or(value).trim();
+    let date = value.get(..10)?;
+    let mut fields = date.split('-');
+    let year = fields.next()?;
+    let month = fields.next()?;
+    let day = fields.next()?;
+    let time = value.get(11..19).unwrap_or("00:00:00");
+    let mut clock = time.split(':');
+    let hour = clock.next()?;
+    let minute = clock.next()?;
+    let second = clock.next()?;
+
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(year),
+            Some('y') => out.push_str(year.get(2..)?),
+            Some('m') => out.push_str(month),
+            Some('d') => out.push_str(day),
+            Some('H') => out.push_str(hour),
+            Some('M') => out.push_str(minute),
+            Some('S') => out.push_str(second),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    Some(out)
+}
+
+fn normalize_path(path: &str, page_id: &str) -> String {
+    let mut segments = path
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    match segments.last_mut() {
+        Some(file) if file.starts_with('.') => *file = format!("{}{}", page_id, file),
+        Some(_) => {}
+        None => segments.push(format!("{}.md", page_id)),
+    }
+    segments.join("/")
+}
+
+pub fn with_suffix(path: &str, page_id: &str) -> String {
+    let suffix = page_id.replace('-', "");
+    let suffix = &suffix[..suffix.len().min(8)];
+    let (dir, file) = match path.rsplit_once('/') {
+        Some((dir, file)) => (Some(dir), file),
+        None => (None, path),
+    };
+    let file = match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}-{}.{}", stem, suffix, ext),
+        _ => format!("{}-{}", file, suffix),
+    };
+    match dir {
+        Some(dir) => format!("{}/{}", dir, file),
+        None => file,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_collapses_separators() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  --Release  Notes v2 -- "), "release-notes-v2");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn slugify_keeps_non_latin_letters() {
+        assert_eq!(slugify("会议记录 2024"), "会议记录-2024");
+        assert_eq!(slugify("日本語のページ"), "日本語のページ");
+        assert_eq!(slugify("Ünïcödé Straße"), "ünïcödé-straße");
+    }
+
+    #[test]
+    fn root_is_leading_literal_directory() {
+        let root = |template: &str| PathTemplate::parse(template).unwrap().root();
+        assert_eq!(root(DEFAULT_PATH_TEMPLATE), "pages");
+        assert_eq!(root("posts/{{date:published_at|%Y}}/{{slug:title}}.md"), "posts");
+        assert_eq!(root("blog/drafts/post-{{id}}.md"), "blog/drafts");
+        assert_eq!(root("{{slug:title}}.md"), "");
+        assert_eq!(root("post-{{id}}.md"), "");
+    }
+
+    #[test]
+    fn with_suffix_appends_short_id_before_extension() {
+        let id = "1a2b3c4d-5e6f-7890-abcd-ef0123456789";
+        assert_eq!(with_suffix("notes/meeting.md", id), "notes/meeting-1a2b3c4d.md");
+        assert_eq!(with_suffix("meeting.md", id), "meeting-1a2b3c4d.md");
+        assert_eq!(with_suffix("notes/meeting", id), "notes/meeting-1a2b3c4d");
+        assert_eq!(with_suffix("notes/.hidden", id), "notes/.hidden-1a2b3c4d");
+        assert_eq!(with_suffix("a.b/page.tar.gz", id), "a.b/page.tar-1a2b3c4d.gz");
+        assert_eq!(with_suffix("page.md", "abc"), "page-abc.md");
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use crate::config::{CaseTransform, PropertyTransform};
+use crate::notion::PropertyValue;
+use crate::template::{format_date, slugify};
+
+pub fn apply_transforms(
+    properties: &mut BTreeMap<String, PropertyValue>,
+    transforms: &BTreeMap<String, PropertyTransform>,
+    key_map: &BTreeMap<String, String>,
+) {
+    for (name, transform) in transforms {
+        // Transforms naming no property on this page are skipped rather than
+        // adding it, so an unknown name never shows up as a null property.
+        let key = if properties.contains_key(name) {
+            Some(name.clone())
+        } else {
+            key_map
+                .iter()
+                .find(|(original, mapped)| *mapped == name && properties.contains_key(*original))
+                .map(|(original, _)| original.clone())
+        };
+        let Some((key, value)) = key.and_then(|key| properties.remove_entry(&key)) else {
+            continue;
+        };
+        if let Some(value) = transform_value(value, transform) {
+            properties.insert(key, value);
+        }
+    }
+}
+
+fn transform_value(value: PropertyValue, transform: &PropertyTransform) -> Option<PropertyValue> {
+    let mut value = value;
+    if is_empty(&value)
+        && let Some(default) = transform.default.as_ref()
+    {
+        value = PropertyValue::from_json(default);
+    }
+
+    if let (Some(separator), PropertyValue::Text(text)) = (transform.split.as_deref(), &value) {
+        value = PropertyValue::List(
+            text.split(separator)
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect(),
+        );
+    }
+
+    value = map_strings(value, |text| {
+        let mut text = transform.map.get(&text).cloned().unwrap_or(text);
+        match transform.case {
+            Some(CaseTransform::Lower) => text = text.to_lowercase(),
+            Some(CaseTransform::Upper) => text = text.to_uppercase(),
+            None => {}
+        }
+        if transform.slugify {
+            text = slugify(&text);
+        }
+        text
+    });
+
+    if let Some(format) = transform.date_format.as_deref() {
+        value = match value {
+            PropertyValue::Date {
+                start,
+                end,
+                time_zone,
+            } => PropertyValue::Date {
+                start: format_date(&start, format).unwrap_or(start),
+                end: end.map(|end| format_date(&end, format).unwrap_or(end)),
+                time_zone,
+            },
+            PropertyValue::Text(text) => {
+                PropertyValue::Text(format_date(&text, format).unwrap_or(text))
+            }
+            other => other,
+        };
+    }
+
+    if let (Some(separator), PropertyValue::List(items)) = (transform.join.as_deref(), &value) {
+        value = PropertyValue::Text(items.join(separator));
+    }
+
+    if transform.drop_empty && is_empty(&value) {
+        return None;
+    }
+    Some(value)
+}
+
+fn map_strings(value: PropertyValue, f: impl Fn(String) -> String) -> PropertyValue {
+    match value {
+        PropertyValue::Text(text) => PropertyValue::Text(f(text)),
+        PropertyValue::List(items) => PropertyValue::List(items.into_iter().map(f).collect()),
+        other => other,
+    }
+}
+
+fn is_empty(value: &PropertyValue) -> bool {
+    match value {
+        PropertyValue::Null => true,
+        PropertyValue::Text(text) => text.trim().is_empty(),
+        PropertyValue::List(items) => items.is_empty(),
+        PropertyValue::Object(value) => value.is_null(),
+        _ => false,
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::{BTreeSet, HashSet};
+
+use anyhow::{anyhow, Result};
+use futures::{stream, StreamExt};
+use log::{info, warn};
+
+use crate::config::{DeletedPagesMode, OutputFormat};
+use crate::manifest::SyncManifest;
+use crate::notion::{Block, PageMetadata};
+use crate::plan::{write_file, EntryKind};
+use crate::render::{relative_path, OutputTarget};
+use crate::sync::{remove_page, save_manifests, sync_page_at};
+use crate::template::{slugify, with_suffix};
+use crate::{AppState, DatabaseState};
+
+/// A `[[page]]` root: the page and everything below it, mirrored into
+/// `directory`.
+#[derive(Clone, Debug)]
+pub struct PageTree {
+    pub root: String,
+    pub directory: String,
+}
+
+/// Path of a tree page: its slugged title inside the parent's directory.
+pub fn tree_page_path(directory: &str, metadata: &PageMetadata) -> String {
+    let name = metadata
+        .title
+        .as_deref()
+        .map(slugify)
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| metadata.id.clone());
+    let directory = directory.trim_matches('/');
+    if directory.is_empty() {
+        format!("{}.md", name)
+    } else {
+        format!("{}/{}.md", directory, name)
+    }
+}
+
+/// Directory holding the children of the page stored at `path`.
+pub fn children_directory(path: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() && !stem.ends_with('/') => stem.to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// Directory holding the rows of an inline database on the page at
+/// `parent_path`, named after its title or else its id. Inline databases on
+/// the same page whose names collide each get a suffix from their id.
+fn child_database_directory(
+    parent_path: &str,
+    databases: &[(String, String)],
+    database_id: &str,
+    title: &str,
+) -> String {
+    let name = |id: &str, title: &str| {
+        Some(slugify(title))
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| id.to_string())
+    };
+    let own = name(database_id, title);
+    let collides = databases
+        .iter()
+        .any(|(id, title)| id != database_id && name(id, title) == own);
+    let own = if collides {
+        with_suffix(&own, database_id)
+    } else {
+        own
+    };
+    format!("{}/{}", children_directory(parent_path), own)
+}
+
+/// Base path of the generated index listing an inline database's rows.
+pub fn child_index_path(
+    parent_path: &str,
+    databases: &[(String, String)],
+    database_id: &str,
+    title: &str,
+) -> String {
+    format!(
+        "{}/index.md",
+        child_database_directory(parent_path, databases, database_id, title)
+    )
+}
+
+/// Sub-pages and inline databases referenced anywhere in a block tree, without
+/// looking inside either.
+pub fn collect_children(
+    blocks: &[Block],
+    pages: &mut Vec<String>,
+    databases: &mut Vec<(String, String)>,
+) {
+    for block in blocks {
+        match block.block_type.as_str() {
+            "child_page" => pages.push(block.id.clone()),
+            "child_database" => {
+                let title = block
+                    .child_database
+                    .as_ref()
+                    .map(|child| child.title.clone())
+                    .unwrap_or_default();
+                databases.push((block.id.clone(), title));
+            }
+            _ => collect_children(&block.children, pages, databases),
+        }
+    }
+}
+
+pub async fn scan_page_tree(
+    state: &AppState,
+    database: &DatabaseState,
+    tree: &PageTree,
+) -> Result<()> {
+    let mut walk = Walk::default();
+    let root = walk
+        .sync_level(state, database, vec![(tree.root.clone(), tree.directory.clone())])
+        .await;
+    walk.descend(state, database, root, true).await;
+
+    if walk.failures == 0 && state.deleted_pages != DeletedPagesMode::Keep {
+        // Only pages this tree synced itself are candidates, never pages some
+        // other database recorded on the same storage.
+        let mut missing = BTreeSet::new();
+        for storage in &database.storage {
+            missing.extend(
+                storage
+                    .manifest()
+                    .pages
+                    .iter()
+                    .filter(|(id, page)| {
+                        page.owner.as_deref() == Some(database.id.as_str())
+                            && !walk.visited.contains(*id)
+                    })
+                    .map(|(id, _)| id.clone()),
+            );
+        }
+        for page_id in missing {
+            if let Err(err) = remove_page(state, database, &page_id).await {
+                warn!("page removal failed {} (tree {}): {err}", page_id, tree.root);
+            }
+        }
+    } else if walk.failures > 0 {
+        warn!(
+            "{} pages failed in tree {}, skipping removal of missing pages",
+            walk.failures, tree.root
+        );
+    }
+    walk.write_indexes(state, database).await;
+    info!("synced {} pages in tree {}", walk.visited.len(), tree.root);
+    save_manifests(state, database).await;
+    if walk.failures > 0 {
+        return Err(anyhow!("{} pages failed", walk.failures));
+    }
+    Ok(())
+}
+
+/// Syncs the rows of inline databases found on the database's pages into
+/// directories beneath those pages, following nested inline databases.
+pub async fn scan_child_databases(state: &AppState, database: &DatabaseState) -> Result<()> {
+    let own = |data_source_id: Option<&str>| {
+        database
+            .data_sources
+            .iter()
+            .any(|data_source| Some(data_source.id.as_str()) == data_source_id)
+    };
+    let mut parents = BTreeSet::new();
+    for storage in &database.storage {
+        parents.extend(
+            storage
+                .manifest()
+                .pages
+                .iter()
+                .filter(|(_, page)| {
+                    !page.child_databases.is_empty() && own(page.data_source_id.as_deref())
+                })
+                .map(|(id, _)| id.clone()),
+        );
+    }
+
+    let mut walk = Walk::default();
+    walk.visited.extend(parents.iter().cloned());
+    walk.descend(state, database, parents.into_iter().collect(), false)
+        .await;
+
+    if walk.failures == 0 && state.deleted_pages != DeletedPagesMode::Keep {
+        let mut missing = BTreeSet::new();
+        for storage in &database.storage {
+            missing.extend(
+                storage
+                    .manifest()
+                    .pages
+                    .iter()
+                    .filter(|(id, page)| {
+                        page.data_source_id.is_some()
+                            && !own(page.data_source_id.as_deref())
+                            && !walk.visited.contains(*id)
+                    })
+                    .map(|(id, _)| id.clone()),
+            );
+        }
+        for page_id in missing {
+            if let Err(err) = remove_page(state, database, &page_id).await {
+                warn!("page removal failed {} (db {}): {err}", page_id, database.id);
+            }
+        }
+    } else if walk.failures > 0 {
+        warn!(
+            "{} inline database pages failed (db {}), skipping removal of missing rows",
+            walk.failures, database.id
+        );
+    }
+    walk.write_indexes(state, database).await;
+    save_manifests(state, database).await;
+    if walk.failures > 0 {
+        return Err(anyhow!("{} inline database pages failed", walk.failures));
+    }
+    Ok(())
+}
+
+/// Breadth-first walk below synced pages, into inline databases and, for page
+/// trees, sub-pages.
+#[derive(Default)]
+struct Walk {
+    visited: HashSet<String>,
+    indexes: Vec<ChildIndex>,
+    failures: usize,
+}
+
+struct ChildIndex {
+    path: String,
+    title: String,
+    rows: Vec<String>,
+}
+
+impl Walk {
+    /// Syncs each unvisited `(page_id, directory)` pair and returns the ids
+    /// that were attempted.
+    async fn sync_level(
+        &mut self,
+        state: &AppState,
+        database: &DatabaseState,
+        mut level: Vec<(String, String)>,
+    ) -> Vec<String> {
+        level.retain(|(page_id, _)| self.visited.insert(page_id.clone()));
+        self.failures += stream::iter(level.clone())
+            .map(|(page_id, directory)| async move {
+                let result = sync_page_at(state, database, &page_id, Some(&directory)).await;
+                if let Err(err) = &result {
+                    warn!("page sync failed {} (db {}): {err}", page_id, database.id);
+                }
+                result.is_err() as usize
+            })
+            .buffer_unordered(database.concurrency)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .sum::<usize>();
+        level.into_iter().map(|(page_id, _)| page_id).collect()
+    }
+
+    async fn descend(
+        &mut self,
+        state: &AppState,
+        database: &DatabaseState,
+        mut parents: Vec<String>,
+        follow_pages: bool,
+    ) {
+        while !parents.is_empty() {
+            let mut next = Vec::new();
+            for page_id in &parents {
+                let Some(page) = database
+                    .storage
+                    .iter()
+                    .find_map(|storage| storage.manifest().pages.get(page_id).cloned())
+                else {
+                    continue;
+                };
+                let path = page.path(page_id);
+                if follow_pages {
+                    let directory = children_directory(&path);
+                    next.extend(
+                        page.child_pages
+                            .iter()
+                            .map(|child| (child.clone(), directory.clone())),
+                    );
+                }
+                for (database_id, title) in &page.child_databases {
+                    match child_database_rows(state, database_id).await {
+                        Ok(rows) => {
+                            let siblings = &page.child_databases;
+                            let directory =
+                                child_database_directory(&path, siblings, database_id, title);
+                            next.extend(rows.iter().map(|row| (row.clone(), directory.clone())));
+                            self.indexes.push(ChildIndex {
+                                path: child_index_path(&path, siblings, database_id, title),
+                                title: title.clone(),
+                                rows,
+                            });
+                        }
+                        Err(err) => {
+                            warn!(
+                                "failed to query inline database {} (db {}): {err}",
+                                database_id, database.id
+                            );
+                            self.failures += 1;
+                        }
+                    }
+                }
+            }
+            parents = self.sync_level(state, database, next).await;
+        }
+    }
+
+    async fn write_indexes(&self, state: &AppState, database: &DatabaseState) {
+        let outputs = database
+            .outputs
+            .iter()
+            .filter(|output| output.format == OutputFormat::Markdown);
+        for output in outputs {
+            for storage in &database.storage {
+                let indexes = {
+                    let manifest = storage.manifest();
+                    self.indexes
+                        .iter()
+                        .map(|index| {
+                            let path = output.page_path(&index.path);
+                            let body = render_index(index, &path, &manifest, output);
+                            (path, body)
+                        })
+                        .collect::<Vec<_>>()
+                };
+                for (path, body) in indexes {
+                    let result = write_file(
+                        state,
+                        database,
+                        storage,
+                        EntryKind::Index,
+                        &path,
+                        body.into_bytes(),
+                    )
+                    .await;
+                    if let Err(err) = result {
+                        warn!(
+                            "failed to write index {} on {} (db {}): {err}",
+                            path, storage.name, database.id
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Row ids of every data source in an inline database.
+async fn child_database_rows(state: &AppState, database_id: &str) -> Result<Vec<String>> {
+    let mut rows = Vec::new();
+    for data_source in state.notion.fetch_database_data_sources(database_id).await? {
+        rows.extend(
+            state
+                .notion
+                .query_data_source_page_ids(&data_source.id, None)
+                .await?,
+        );
+    }
+    Ok(rows)
+}
+
+fn render_index(
+    index: &ChildIndex,
+    path: &str,
+    manifest: &SyncManifest,
+    output: &OutputTarget,
+) -> String {
+    let mut out = format!("# {}\n\n", index.title);
+    for row in &index.rows {
+        let Some(page) = manifest.pages.get(row) else {
+            continue;
+        };
+        let target = output.page_path(&page.path(row));
+        out.push_str(&format!(
+            "- [{}]({})\n",
+            page.title.as_deref().unwrap_or(row),
+            relative_path(path, &target)
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn block(id: &str, block_type: &str, children: Vec<Block>) -> Block {
+        let mut value = json!({
+            "id": id,
+            "type": block_type,
+            "has_children": !children.is_empty(),
+        });
+        if block_type == "child_page" || block_type == "child_database" {
+            value[block_type] = json!({ "title": id.to_uppercase() });
+        }
+        let mut block: Block = serde_json::from_value(value).unwrap();
+        block.children = children;
+        block
+    }
+
+    #[test]
+    fn collect_children_stops_at_sub_pages() {
+        // root page
+        // ├── toggle
+        // │   ├── child_page "a"
+        // │   │   └── child_page "a1" (grandchild, belongs to "a")
+        // │   └── child_database "db"
+        // └── child_page "b"
+        //     └── child_database "b-db" (belongs to "b")
+        let blocks = vec![
+            block(
+                "toggle",
+                "toggle",
+                vec![
+                    block("a", "child_page", vec![block("a1", "child_page", vec![])]),
+                    block("db", "child_database", vec![]),
+                ],
+            ),
+            block("b", "child_page", vec![block("b-db", "child_database", vec![])]),
+        ];
+
+        let mut pages = Vec::new();
+        let mut databases = Vec::new();
+        collect_children(&blocks, &mut pages, &mut databases);
+
+        assert_eq!(pages, vec!["a", "b"]);
+        assert_eq!(databases, vec![("db".to_string(), "DB".to_string())]);
+    }
+
+    #[test]
+    fn colliding_inline_databases_get_id_suffixes() {
+        let databases = vec![
+            ("1111aaaa-0000".to_string(), "Tasks".to_string()),
+            ("2222bbbb-0000".to_string(), "tasks!".to_string()),
+            ("3333cccc-0000".to_string(), "Notes".to_string()),
+            ("4444dddd-0000".to_string(), String::new()),
+        ];
+        let directory =
+            |id: &str, title: &str| child_database_directory("docs/page.md", &databases, id, title);
+
+        assert_eq!(directory("1111aaaa-0000", "Tasks"), "docs/page/tasks-1111aaaa");
+        assert_eq!(directory("2222bbbb-0000", "tasks!"), "docs/page/tasks-2222bbbb");
+        assert_eq!(directory("3333cccc-0000", "Notes"), "docs/page/notes");
+        assert_eq!(directory("4444dddd-0000", ""), "docs/page/4444dddd-0000");
+        assert_eq!(
+            child_index_path("docs/page.md", &databases, "3333cccc-0000", "Notes"),
+            "docs/page/notes/index.md"
+        );
+
+        // An untitled database falls back to its id, which can collide too.
+        let databases = vec![
+            ("5555eeee".to_string(), String::new()),
+            ("6666ffff".to_string(), "5555eeee".to_string()),
+        ];
+        assert_eq!(
+            child_database_directory("page.md", &databases, "5555eeee", ""),
+            "page/5555eeee-5555eeee"
+        );
+        assert_eq!(
+            child_database_directory("page.md", &databases, "6666ffff", "5555eeee"),
+            "page/5555eeee-6666ffff"
+        );
+    }
+}
@@ -55,7 +55,20 @@ pub async fn handle_webhook(
         }
     }
 
+    let event_type = payload
+        .get("type")
+        .and_then(|value| value.as_str())
+        .unwrap_or_default();
+
     if let Some(page_id) = extract_page_id(&payload) {
+        if event_type == "page.deleted" {
+            spawn_sync(&state, move |state| async move {
+                if let Err(err) = sync::remove_page_by_id(&state, &page_id).await {
+                    error!("page removal failed {}: {err}", page_id);
+                }
+            });
+            return StatusCode::OK.into_response();
+        }
         spawn_sync(&state, move |state| async move {
             if let Err(err) = sync::sync_page_by_id(&state, &page_id).await {
                 error!("page sync failed {}: {err}", page_id);
@@ -65,7 +78,8 @@ pub async fn handle_webhook(
     }
 
     if let Some(data_source_id) = extract_data_source_id(&payload) {
-        let database = state.databases.iter().find(|db| {
+        let databases = state.databases();
+        let database = databases.iter().find(|db| {
             db.data_sources.iter().any(|ds| ds.id == data_source_id)
         });
         let Some(database) = database else {
@@ -82,7 +96,8 @@ pub async fn handle_webhook(
     }
 
     if let Some(database_id) = extract_database_id(&payload) {
-        let database = state.databases.iter().find(|db| db.id == database_id);
+        let databases = state.databases();
+        let database = databases.iter().find(|db| db.id == database_id);
         let Some(database) = database else {
             info!("database {} not configured, skipping", database_id);
             return StatusCode::OK.into_response();
@@ -104,6 +119,13 @@ fn extract_page_id(payload: &Value) -> Option<String> {
         return Some(page_id.to_string());
     }
 
+    if let Some(entity) = payload.get("entity")
+        && entity.get("type").and_then(|v| v.as_str()) == Some("page")
+        && let Some(page_id) = entity.get("id").and_then(|v| v.as_str())
+    {
+        return Some(page_id.to_string());
+    }
+
     payload
         .get("data")
         .and_then(|data| data.get("id"))